@@ -0,0 +1,72 @@
+use std::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+};
+
+use crate::{Branches, Node, TreeSlice, TreeVec, TREE_SIZE_SIZE};
+
+/// Renders one value per line, with children in the order they have been written below their
+/// parent, indented by two spaces per level. Size headers which exceed the bytes of their parent
+/// and invalid values are rendered as `<corrupt>` rather than panicking, as long as `N`
+/// implements [`Node::try_read_value`].
+impl<N> Debug for TreeSlice<N>
+where
+    N: Node,
+    N::Value: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        /// Item on the stack of lines still to render.
+        enum Line<'a, N> {
+            Subtree(&'a TreeSlice<N>),
+            Corrupt,
+        }
+
+        let mut stack = vec![(0, Line::Subtree(self))];
+        let mut first = true;
+        while let Some((depth, line)) = stack.pop() {
+            if !first {
+                writeln!(f)?;
+            }
+            first = false;
+            write!(f, "{:indent$}", "", indent = 2 * depth)?;
+            let subtree = match line {
+                Line::Subtree(subtree) if subtree.bytes.len() >= TREE_SIZE_SIZE => subtree,
+                _ => {
+                    write!(f, "<corrupt>")?;
+                    continue;
+                }
+            };
+            let remaining = subtree.bytes.len() - TREE_SIZE_SIZE;
+            let Some((size_value, value)) = N::try_read_value(&subtree.bytes[..remaining]) else {
+                write!(f, "<corrupt>")?;
+                continue;
+            };
+            write!(f, "{value:?}")?;
+            let mut branches = Branches::<N> {
+                _node_type: PhantomData,
+                bytes: &subtree.bytes[..(remaining - size_value)],
+                len: None,
+            };
+            // Like in `Walk` the first written child ends up on top of the stack, but each child
+            // is checked. Corrupt bytes are located in front of the children read so far.
+            while let Some(child) = branches.checked_next() {
+                let line = match child {
+                    Ok(child) => Line::Subtree(child),
+                    Err(_) => Line::Corrupt,
+                };
+                stack.push((depth + 1, line));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<N> Debug for TreeVec<N>
+where
+    N: Node,
+    N::Value: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.as_tree_slice().fmt(f)
+    }
+}
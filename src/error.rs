@@ -0,0 +1,128 @@
+use std::{
+    error::Error,
+    fmt::{self, Debug, Display, Formatter},
+    io,
+};
+
+use crate::{TreeBuilder, TreeSize};
+
+/// Errors which may occur inspecting the binary representation of a tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeError {
+    /// No subtree starts at the given offset.
+    NoSubtreeAt { offset: usize },
+    /// The bytes do not describe a tree in the expected format.
+    Malformed { reason: &'static str },
+    /// A parent pointer representation does not describe a single tree.
+    InvalidParents { reason: &'static str },
+    /// A size header claims more bytes than are available in front of it.
+    SizeOutOfBounds {
+        /// Offset of the first byte of the size header.
+        offset: usize,
+        /// Size of the subtree according to the header, excluding the header itself.
+        claimed: TreeSize,
+        /// Number of bytes in front of the size header, within the bounds of its parent.
+        remaining: usize,
+    },
+    /// A serialized value exceeds the limit configured with
+    /// [`crate::TreeBuilder::with_max_value_size`].
+    ValueTooLarge {
+        /// Offset at which the value would have been written.
+        offset: usize,
+        /// Number of bytes of the serialized value.
+        size: usize,
+        limit: usize,
+    },
+    /// A buffer can not hold all nodes of the tree.
+    BufferTooSmall { capacity: usize, required: usize },
+    /// The checksum stored in the footer does not match the checksum of the tree.
+    ChecksumMismatch { stored: u32, computed: u32 },
+}
+
+impl Display for TreeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TreeError::NoSubtreeAt { offset } => {
+                write!(f, "No subtree starts at offset {offset}.")
+            }
+            TreeError::Malformed { reason } => write!(f, "Malformed tree: {reason}"),
+            TreeError::InvalidParents { reason } => write!(f, "Invalid parent array: {reason}"),
+            TreeError::SizeOutOfBounds {
+                offset,
+                claimed,
+                remaining,
+            } => write!(
+                f,
+                "Size header at offset {offset} claims {claimed} bytes, but only {remaining} bytes \
+                are remaining."
+            ),
+            TreeError::ValueTooLarge {
+                offset,
+                size,
+                limit,
+            } => write!(
+                f,
+                "Value at offset {offset} takes {size} bytes, exceeding the limit of {limit} bytes."
+            ),
+            TreeError::BufferTooSmall { capacity, required } => write!(
+                f,
+                "Buffer holds {capacity} elements, but tree has {required} nodes."
+            ),
+            TreeError::ChecksumMismatch { stored, computed } => write!(
+                f,
+                "Checksum mismatch: footer stores {stored:#010x}, but tree has {computed:#010x}."
+            ),
+        }
+    }
+}
+
+impl Error for TreeError {}
+
+/// Returned by [`TreeBuilder::finish`]. Holds on to the builder, so the writer is not lost. E.g.
+/// the missing parent node can still be written, or the writer can be recovered with
+/// [`TreeBuilder::finish_forest`]. Converts into an [`io::Error`], so `?` works in functions
+/// returning [`io::Result`].
+pub struct FinishError<N, W> {
+    error: io::Error,
+    builder: TreeBuilder<N, W>,
+}
+
+impl<N, W> FinishError<N, W> {
+    pub(crate) fn new(error: io::Error, builder: TreeBuilder<N, W>) -> Self {
+        Self { error, builder }
+    }
+
+    /// Kind of the underlying [`io::Error`]. [`io::ErrorKind::InvalidData`] unless exactly one
+    /// root remained, otherwise the kind of the error flushing the writer.
+    pub fn kind(&self) -> io::ErrorKind {
+        self.error.kind()
+    }
+
+    /// The builder [`TreeBuilder::finish`] has been called on, in the same state as before.
+    pub fn into_builder(self) -> TreeBuilder<N, W> {
+        self.builder
+    }
+}
+
+// Implemented manually, since deriving would require `N` and `W` to be `Debug`.
+impl<N, W> Debug for FinishError<N, W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FinishError")
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<N, W> Display for FinishError<N, W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.error, f)
+    }
+}
+
+impl<N, W> Error for FinishError<N, W> {}
+
+impl<N, W> From<FinishError<N, W>> for io::Error {
+    fn from(error: FinishError<N, W>) -> Self {
+        error.error
+    }
+}
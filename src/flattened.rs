@@ -0,0 +1,132 @@
+use std::ops::RangeBounds;
+
+use crate::{Node, TreeBuilder, TreeError, TreeSlice, TreeVec};
+
+impl<N> TreeVec<N> {
+    /// Builds a tree from the representation returned by [`TreeSlice::flatten`]. Each element
+    /// holds the value of a node in pre-order and the number of nodes in its subtree, including
+    /// itself.
+    ///
+    /// Returns [`TreeError::Malformed`] if `nodes` is empty, a count is `0`, the subtree of a
+    /// node exceeds the subtree of its parent, or the root does not span all nodes.
+    pub fn from_flattened(nodes: &[(N::Value, usize)]) -> Result<Self, TreeError>
+    where
+        N: Node,
+    {
+        /// A node, whose children are still being written.
+        struct Frame {
+            index: usize,
+            /// One past the pre-order index of the last node in the subtree.
+            end: usize,
+            num_children: usize,
+        }
+
+        match nodes.first() {
+            None => return Err(malformed("There must be at least one node.")),
+            Some(&(_, count)) if count != nodes.len() => {
+                return Err(malformed("Subtree of the root must span all nodes."))
+            }
+            _ => (),
+        }
+        let mut builder = TreeBuilder::<N, _>::new(Vec::new());
+        let mut write = |frame: Frame| {
+            builder
+                .write_node(&nodes[frame.index].0, frame.num_children)
+                .expect("Writing to Vec must not fail");
+        };
+        let mut stack: Vec<Frame> = Vec::new();
+        for (index, &(_, count)) in nodes.iter().enumerate() {
+            // Write all subtrees which are complete, before descending into the next one.
+            while stack.last().is_some_and(|frame| frame.end <= index) {
+                write(stack.pop().unwrap());
+            }
+            if count == 0 {
+                return Err(malformed("Each subtree must at least contain its root."));
+            }
+            let end = index + count;
+            if let Some(parent) = stack.last_mut() {
+                if end > parent.end {
+                    return Err(malformed(
+                        "Subtree must not exceed the subtree of its parent.",
+                    ));
+                }
+                parent.num_children += 1;
+            }
+            stack.push(Frame {
+                index,
+                end,
+                num_children: 0,
+            });
+        }
+        while let Some(frame) = stack.pop() {
+            write(frame);
+        }
+        Ok(TreeVec::new(
+            builder.finish().expect("Flushing Vec must not fail"),
+        ))
+    }
+}
+
+impl<N> TreeSlice<N> {
+    /// Values of all nodes in pre-order, with children in the order they have been written, each
+    /// paired with the number of nodes in its subtree (including itself). Together they determine
+    /// the tree uniquely. Inverse of [`TreeVec::from_flattened`].
+    pub fn flatten(&self) -> Vec<(N::Value, usize)>
+    where
+        N: Node,
+    {
+        self.values_pre_order()
+            .zip(self.preorder_size_prefix())
+            .collect()
+    }
+
+    /// All subtrees, whose number of nodes (including the root) lies within `range`. Subtrees are
+    /// yielded in pre-order of their roots, with children in the order they have been written.
+    pub fn subtrees_with_node_count<R>(&self, range: R) -> impl Iterator<Item = &TreeSlice<N>>
+    where
+        N: Node,
+        R: RangeBounds<usize>,
+    {
+        self.pre_order()
+            .zip(self.preorder_size_prefix())
+            .filter(move |(_, count)| range.contains(count))
+            .map(|(subtree, _)| subtree)
+    }
+
+    /// Number of nodes in the subtree of each node (including itself), indexed by the pre-order
+    /// index of the node, with children in the order they have been written. The subtree of node
+    /// `i` consists of the nodes `i..(i + sizes[i])`, so node `j` is a descendant of node `i` if
+    /// and only if `i < j < i + sizes[i]`.
+    pub fn preorder_size_prefix(&self) -> Vec<usize>
+    where
+        N: Node,
+    {
+        let mut sizes = Vec::new();
+        // Pre-order indices and end offsets of the nodes on the path from the root to the current
+        // node.
+        let mut path: Vec<(usize, usize)> = Vec::new();
+        for (index, subtree) in self.pre_order().enumerate() {
+            let end = self.end_of(subtree);
+            // Subtrees visited earlier, which are not ancestors, end before this one. They are
+            // complete.
+            while path
+                .last()
+                .is_some_and(|&(_, ancestor_end)| ancestor_end < end)
+            {
+                let (start, _) = path.pop().unwrap();
+                sizes[start] = index - start;
+            }
+            sizes.push(0);
+            path.push((index, end));
+        }
+        let num_nodes = sizes.len();
+        for (start, _) in path {
+            sizes[start] = num_nodes - start;
+        }
+        sizes
+    }
+}
+
+fn malformed(reason: &'static str) -> TreeError {
+    TreeError::Malformed { reason }
+}
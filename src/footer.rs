@@ -0,0 +1,75 @@
+use std::{io, marker::PhantomData};
+
+use crate::{TreeBuilder, TreeError, TreeVec};
+
+/// Length of the footer appended by [`TreeBuilder::finish_with_crc_footer`] in bytes.
+const CRC_FOOTER_SIZE: usize = 4;
+
+impl<N> TreeBuilder<N, Vec<u8>> {
+    /// Finishes building a tree in memory and appends a CRC-32 (IEEE) of all bytes of the tree as
+    /// a little endian footer. Use [`TreeVec::with_crc_footer`] to read the result.
+    ///
+    /// Fails like [`TreeBuilder::finish`] unless exactly one root remains, so the checksum always
+    /// covers a single tree.
+    pub fn finish_with_crc_footer(self) -> io::Result<Vec<u8>> {
+        let mut bytes = self.finish()?;
+        let crc = crc32(&bytes);
+        bytes.extend_from_slice(&crc.to_le_bytes());
+        Ok(bytes)
+    }
+}
+
+impl<N> TreeVec<N> {
+    /// Takes ownership of bytes written by [`TreeBuilder::finish_with_crc_footer`]. The footer is
+    /// not part of the tree, so [`crate::TreeSlice::read_node`] and friends work as usual. No
+    /// checks are performed, call [`Self::verify_footer`] for that.
+    ///
+    /// Returns [`TreeError::Malformed`] if `bytes` is too short to hold the footer.
+    pub fn with_crc_footer(bytes: Vec<u8>) -> Result<Self, TreeError> {
+        if bytes.len() < CRC_FOOTER_SIZE {
+            return Err(TreeError::Malformed {
+                reason: "Bytes are too short to hold a CRC footer.",
+            });
+        }
+        Ok(TreeVec {
+            _node_type: PhantomData,
+            bytes,
+            footer_len: CRC_FOOTER_SIZE,
+        })
+    }
+
+    /// Recomputes the CRC-32 of the tree and compares it with the one stored in the footer.
+    ///
+    /// Returns [`TreeError::ChecksumMismatch`] if they differ, or [`TreeError::Malformed`] if the
+    /// tree has not been constructed with [`Self::with_crc_footer`].
+    pub fn verify_footer(&self) -> Result<(), TreeError> {
+        if self.footer_len != CRC_FOOTER_SIZE {
+            return Err(TreeError::Malformed {
+                reason: "Tree has no CRC footer.",
+            });
+        }
+        let (tree, footer) = self.bytes.split_at(self.bytes.len() - CRC_FOOTER_SIZE);
+        let stored = u32::from_le_bytes(footer.try_into().unwrap());
+        let computed = crc32(tree);
+        if stored == computed {
+            Ok(())
+        } else {
+            Err(TreeError::ChecksumMismatch { stored, computed })
+        }
+    }
+}
+
+/// CRC-32 with the reflected IEEE polynomial, as used by e.g. zlib and PNG. Computed bitwise, since
+/// integrity checks are not expected to be on the hot path.
+fn crc32(bytes: &[u8]) -> u32 {
+    const POLYNOMIAL: u32 = 0xEDB8_8320;
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (POLYNOMIAL & mask);
+        }
+    }
+    !crc
+}
@@ -0,0 +1,47 @@
+use std::io::{self, Write};
+
+use crate::{Node, TreeBuilder};
+
+/// A recursively defined tree in memory, which can be serialized with
+/// [`TreeBuilder::write_tree`]. Implement this for your own `struct` or `enum` to avoid counting
+/// children by hand.
+pub trait IntoTree {
+    /// Value stored in each node. Must match the [`Node::Value`] of the tree written.
+    type Value;
+
+    /// Value of the root of this tree.
+    fn value(&self) -> &Self::Value;
+
+    /// Children of the root. The first child yielded is written first, so it is the first child
+    /// returned by [`crate::TreeSlice::child`].
+    fn children(&self) -> impl Iterator<Item = &Self>;
+}
+
+impl<N, W> TreeBuilder<N, W>
+where
+    N: Node,
+{
+    /// Writes `tree` and all its descendants as one subtree. Children are written before their
+    /// parents and the number of children is counted for you. Traverses the tree iteratively, so
+    /// deep trees do not overflow the stack.
+    pub fn write_tree<T>(&mut self, tree: &T) -> io::Result<()>
+    where
+        T: IntoTree<Value = N::Value>,
+        W: Write,
+    {
+        // Post-order traversal. For each node we remember its remaining children and how many
+        // of them have been written already.
+        let mut stack = vec![(tree, tree.children(), 0)];
+        while let Some((node, children, num_written)) = stack.last_mut() {
+            if let Some(child) = children.next() {
+                *num_written += 1;
+                stack.push((child, child.children(), 0));
+            } else {
+                let (node, num_children) = (*node, *num_written);
+                stack.pop();
+                self.write_node(node.value(), num_children)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,10 +1,44 @@
 //! Write and read tree graphs to and from contigious blocks of memory.
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
+
+#[cfg(feature = "content-id")]
+mod content_id;
+mod debug;
+mod error;
+mod flattened;
+mod footer;
+mod into_tree;
+mod metrics;
+mod packed_bool;
+mod parent_array;
+mod rewrite;
+mod seekable;
+mod shared;
+mod spec;
+mod streaming_validator;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod value_index;
+
+pub use error::{FinishError, TreeError};
+pub use into_tree::IntoTree;
+pub use metrics::TreeMetrics;
+pub use packed_bool::{PackedBoolBuilder, PackedBools, PackedChild};
+pub use seekable::{SeekBranches, SeekNode, SeekableTree};
+pub use shared::TreeArc;
+pub use spec::{StructuralSpec, ValidationError};
+pub use streaming_validator::{Progress, StreamingValidator};
+pub use value_index::{IndexingTreeBuilder, ValueIndex};
 
 use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    hash::Hash,
     io::{self, Write},
     marker::PhantomData,
     mem::size_of,
-    ops::Deref,
+    ops::{Deref, Range},
+    sync::mpsc::{SendError, SyncSender},
 };
 
 /// Used to store the binary sizes of [`TreeVec`]s and [`TreeSlice`]s in bytes. This would usually be
@@ -26,6 +60,13 @@ pub trait Node {
     /// The value type associated with each node in the tree.
     type Value;
 
+    /// Byte order of the size headers of each node. Trees must be read with a node type using the
+    /// same byte order they have been written with. Otherwise the sizes are garbage, and
+    /// traversing the tree yields wrong results or panics. [`Branches::checked_next`] is likely to
+    /// report the mismatch as [`TreeError::SizeOutOfBounds`] though. Use [`BigEndianSizes`] to change
+    /// the byte order for an existing node type.
+    const SIZE_ENDIANNESS: Endianness = Endianness::Little;
+
     /// Writes the value, so [`Self::read_value`] can extract it again. In case of success the
     /// number of bytes written is returned.
     fn write_value<W>(writer: &mut W, value: &Self::Value) -> io::Result<usize>
@@ -35,6 +76,138 @@ pub trait Node {
     /// Reads the value from a raw binary representation. Reads the value from the back of the
     /// passed slice.
     fn read_value(bytes: &[u8]) -> (usize, Self::Value);
+
+    /// Like [`Self::read_value`], but returns `None` instead of panicking, if `bytes` do not hold
+    /// a valid value. E.g. because there are fewer bytes than the value requires. The size
+    /// returned never exceeds the length of `bytes`. [`TreeSlice::validate`] and
+    /// [`Branches::checked_next`] rely on this to reject malformed trees from untrusted sources.
+    ///
+    /// The default implementation calls [`Self::read_value`], so node types whose `read_value` may
+    /// panic should override it.
+    fn try_read_value(bytes: &[u8]) -> Option<(usize, Self::Value)> {
+        let (size, value) = Self::read_value(bytes);
+        (size <= bytes.len()).then_some((size, value))
+    }
+}
+
+/// A [`Node`] whose values can also be read without allocating, borrowing them from the binary
+/// representation instead. E.g. `&str` for [`Utf8`] or `&[u8]` for [`Bytes`]. See
+/// [`TreeSlice::read_node_borrowed`].
+pub trait BorrowingNode: Node {
+    /// Value type pointing into the bytes of the tree.
+    type Borrowed<'a>;
+
+    /// Like [`Node::read_value`], but borrows the value from `bytes`.
+    fn read_borrowed(bytes: &[u8]) -> (usize, Self::Borrowed<'_>);
+}
+
+/// Byte order of the size headers in the binary representation of a tree. See
+/// [`Node::SIZE_ENDIANNESS`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+impl Endianness {
+    fn encode(self, size: TreeSize) -> [u8; TREE_SIZE_SIZE] {
+        match self {
+            Endianness::Little => size.to_le_bytes(),
+            Endianness::Big => size.to_be_bytes(),
+        }
+    }
+
+    fn decode(self, bytes: [u8; TREE_SIZE_SIZE]) -> TreeSize {
+        match self {
+            Endianness::Little => TreeSize::from_le_bytes(bytes),
+            Endianness::Big => TreeSize::from_be_bytes(bytes),
+        }
+    }
+}
+
+/// Reads the size header at the end of `bytes`, which must be at least [`TREE_SIZE_SIZE`] long.
+fn read_size<N: Node>(bytes: &[u8]) -> TreeSize {
+    let size_bytes = bytes[(bytes.len() - TREE_SIZE_SIZE)..].try_into().unwrap();
+    N::SIZE_ENDIANNESS.decode(size_bytes)
+}
+
+/// Skips the subtree at the end of `bytes`, by reading its size header. Returns the bytes in front
+/// of the subtree. E.g. the earlier written siblings of the subtree. This is the step
+/// [`Branches`] takes to advance to the next branch, exposed for custom parsers.
+///
+/// Returns [`TreeError::Malformed`] if `bytes` is too short to hold the size header, or
+/// [`TreeError::SizeOutOfBounds`] if the size header claims more bytes than there are in front of
+/// it. Its offset is relative to the start of `bytes`.
+pub fn skip_subtree<N: Node>(bytes: &[u8]) -> Result<&[u8], TreeError> {
+    let total_size = bytes.len();
+    if total_size < TREE_SIZE_SIZE {
+        return Err(TreeError::Malformed {
+            reason: "Branch is too small to hold a size header.",
+        });
+    }
+    let tree_size = read_size::<N>(bytes);
+    let remaining = total_size - TREE_SIZE_SIZE;
+    if tree_size > remaining as TreeSize {
+        return Err(TreeError::SizeOutOfBounds {
+            offset: remaining,
+            claimed: tree_size,
+            remaining,
+        });
+    }
+    Ok(&bytes[..(remaining - tree_size as usize)])
+}
+
+/// Reverses the byte order of every value in the tree in place, e.g. to migrate a tree written
+/// with [`LeI32`] to [`BeI32`] without rebuilding it. This only works for node types with values of
+/// fixed width, since each value is assumed to occupy exactly `WIDTH` bytes. Size headers are left
+/// untouched, so `N` must match their byte order, but its values are never decoded.
+///
+/// All size headers are checked before the first byte is changed. If a subtree is too small to
+/// hold its size header and value, [`TreeError::Malformed`] is returned, and if a size header
+/// exceeds the bytes of its parent [`TreeError::SizeOutOfBounds`]. E.g. because the values are
+/// not `WIDTH` bytes wide. `bytes` are left untouched in either case.
+pub fn swap_value_endianness<N: Node, const WIDTH: usize>(
+    bytes: &mut [u8],
+) -> Result<(), TreeError> {
+    // Locate all values first, so nothing is changed if the tree turns out to be malformed.
+    let mut value_starts = Vec::new();
+    // Start and end of subtrees not visited yet
+    let mut stack = vec![(0, bytes.len())];
+    while let Some((start, end)) = stack.pop() {
+        let value_start =
+            (end - start)
+                .checked_sub(TREE_SIZE_SIZE + WIDTH)
+                .ok_or(TreeError::Malformed {
+                    reason: "Subtree is too small to hold its size header and value.",
+                })?
+                + start;
+        value_starts.push(value_start);
+        // Peel off the children from the back, like `Branches` does.
+        let mut children_end = value_start;
+        while children_end > start {
+            let remaining = skip_subtree::<N>(&bytes[start..children_end])
+                .map_err(|error| match error {
+                    TreeError::SizeOutOfBounds {
+                        offset,
+                        claimed,
+                        remaining,
+                    } => TreeError::SizeOutOfBounds {
+                        offset: start + offset,
+                        claimed,
+                        remaining,
+                    },
+                    error => error,
+                })?
+                .len();
+            let child_start = start + remaining;
+            stack.push((child_start, children_end));
+            children_end = child_start;
+        }
+    }
+    for value_start in value_starts {
+        bytes[value_start..(value_start + WIDTH)].reverse();
+    }
+    Ok(())
 }
 
 /// Serializes a tree data structure in a depth first manner.
@@ -46,6 +219,16 @@ pub struct TreeBuilder<N, W> {
     open_node_sizes: Vec<TreeSize>,
     /// Writer we serialize the stream into.
     writer: W,
+    /// Upper bound for the number of bytes a serialized value may occupy, if any.
+    max_value_size: Option<usize>,
+    /// Upper bound for the length of `open_node_sizes`, if any.
+    max_open_nodes: Option<usize>,
+    /// Total number of bytes written so far. Equals the sum of `open_node_sizes`, but is kept
+    /// separately, so it need not be recomputed for every node.
+    bytes_written: TreeSize,
+    /// Byte range of each node written so far, in the order they have been written. Only
+    /// recorded after [`TreeBuilder::with_index`].
+    index: Option<Vec<Range<usize>>>,
 }
 
 impl<N, W> TreeBuilder<N, W> {
@@ -54,6 +237,57 @@ impl<N, W> TreeBuilder<N, W> {
             _node_type: PhantomData,
             open_node_sizes: Vec::new(),
             writer,
+            max_value_size: None,
+            max_open_nodes: None,
+            bytes_written: 0,
+            index: None,
+        }
+    }
+
+    /// Reserves capacity for at least `num_open_nodes` nodes without a parent. Their number peaks
+    /// with the depth and fan-out of the tree, e.g. at the number of leaves written before their
+    /// parent. Reserving avoids reallocations while building. Does not change the output.
+    pub fn reserve(&mut self, num_open_nodes: usize) {
+        self.open_node_sizes.reserve(num_open_nodes);
+    }
+
+    /// Limits the number of bytes the serialized value of each node may occupy. Afterwards
+    /// [`Self::write_node`] rejects larger values, protecting readers which assume such a bound,
+    /// e.g. [`SeekableTree`]. Values are serialized into an intermediate buffer first, so nothing
+    /// is written for a rejected value.
+    pub fn with_max_value_size(mut self, limit: usize) -> Self {
+        self.max_value_size = Some(limit);
+        self
+    }
+
+    /// Records the byte range of every node while it is written, so [`Self::finish_with_index`]
+    /// can return them without traversing the finished tree. Takes two machine words of memory per
+    /// node. Call this before writing the first node. Otherwise the index would miss the nodes
+    /// written earlier, so nothing is recorded and [`Self::finish_with_index`] fails.
+    pub fn with_index(mut self) -> Self {
+        self.index = (self.bytes_written == 0).then(Vec::new);
+        self
+    }
+
+    /// Limits the number of nodes without a parent, which are tracked while building. Afterwards
+    /// [`Self::write_node`] and [`Self::write_subtree`] fail with [`io::ErrorKind::InvalidInput`]
+    /// instead of exceeding `limit`. This bounds the memory used for bookkeeping, e.g. if a
+    /// generator from untrusted input writes millions of leaves before their parent. Unlimited by
+    /// default.
+    pub fn with_max_open_nodes(mut self, limit: usize) -> Self {
+        self.max_open_nodes = Some(limit);
+        self
+    }
+
+    /// Fails with [`io::ErrorKind::InvalidInput`] if `num_open` nodes without a parent exceed the
+    /// limit set with [`Self::with_max_open_nodes`].
+    fn check_open_nodes(&self, num_open: usize) -> io::Result<()> {
+        match self.max_open_nodes {
+            Some(limit) if num_open > limit => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Writing the node would exceed the limit of {limit} open subtrees."),
+            )),
+            _ => Ok(()),
         }
     }
 
@@ -64,58 +298,387 @@ impl<N, W> TreeBuilder<N, W> {
     /// * `value`: Value associated with the node
     /// * `num_children`: This node will be the parent node of the last `num_children` nodes written
     ///   which do not have a parent yet.
+    ///
+    /// If the serialized value exceeds the limit set with [`Self::with_max_value_size`], an error
+    /// of kind [`io::ErrorKind::InvalidInput`] wrapping [`TreeError::ValueTooLarge`] is returned.
+    /// The same kind of error is returned if `num_children` exceeds the number of nodes without
+    /// a parent. Nothing is written in either case.
     pub fn write_node(&mut self, value: &N::Value, num_children: usize) -> io::Result<()>
     where
         N: Node,
         W: Write,
     {
+        let num_open = self.open_node_sizes.len();
+        if num_children > num_open {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Requested {num_children} children, but only {num_open} subtrees are open."
+                ),
+            ));
+        }
+        self.check_open_nodes(num_open - num_children + 1)?;
         // All previous children have been written and are immediate predecessors to this node.
         // Layout: children, value, totalsize
-        let size_value: TreeSize = N::write_value(&mut self.writer, value)? as TreeSize;
+        let size_value = match self.max_value_size {
+            None => N::write_value(&mut self.writer, value)?,
+            Some(limit) => {
+                let mut value_bytes = Vec::new();
+                let size = N::write_value(&mut value_bytes, value)?;
+                if size > limit {
+                    let error = TreeError::ValueTooLarge {
+                        offset: self.bytes_written as usize,
+                        size,
+                        limit,
+                    };
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, error));
+                }
+                self.writer.write_all(&value_bytes)?;
+                size
+            }
+        } as TreeSize;
         let size_children: TreeSize = self
             .open_node_sizes
             .drain((self.open_node_sizes.len() - num_children)..)
             .sum();
         let total_size = size_value + size_children;
-        self.writer.write_all(&total_size.to_le_bytes())?;
+        self.writer
+            .write_all(&N::SIZE_ENDIANNESS.encode(total_size))?;
         // We write the size, without the size of the size value itself. However, then accounting
         // for all the childern it must of course be added.
         self.open_node_sizes
             .push(total_size + TREE_SIZE_SIZE as TreeSize);
+        self.bytes_written += size_value + TREE_SIZE_SIZE as TreeSize;
+        if let Some(ranges) = &mut self.index {
+            let end = self.bytes_written as usize;
+            let start = end - (total_size as usize + TREE_SIZE_SIZE);
+            ranges.push(start..end);
+        }
+        Ok(())
+    }
+
+    /// Copies an already serialized subtree into the output. Afterwards it counts as a single
+    /// node without a parent, which can be adopted by the next calls to [`Self::write_node`].
+    pub fn write_subtree(&mut self, subtree: &TreeSlice<N>) -> io::Result<()>
+    where
+        N: Node,
+        W: Write,
+    {
+        self.check_open_nodes(self.open_node_sizes.len() + 1)?;
+        if let Some(ranges) = &mut self.index {
+            let start = self.bytes_written as usize;
+            ranges.extend(
+                subtree
+                    .pre_order()
+                    .map(|node| (start + subtree.offset_of(node))..(start + subtree.end_of(node))),
+            );
+        }
+        self.writer.write_all(&subtree.bytes)?;
+        self.open_node_sizes.push(subtree.bytes.len() as TreeSize);
+        self.bytes_written += subtree.bytes.len() as TreeSize;
         Ok(())
     }
 
+    /// Adds a node to the tree, whose children are copies of the already serialized `children`.
+    /// Children are written in the order passed, so the first element of `children` is the first
+    /// written child of the new node.
+    pub fn write_node_with_children(
+        &mut self,
+        value: &N::Value,
+        children: &[&TreeSlice<N>],
+    ) -> io::Result<()>
+    where
+        N: Node,
+        W: Write,
+    {
+        for child in children {
+            self.write_subtree(child)?;
+        }
+        self.write_node(value, children.len())
+    }
+
     /// Call this once every node has been written. Flushes the output and returns the inner writer
-    /// in case you want to use it for something else.
-    pub fn finish(mut self) -> io::Result<W>
+    /// in case you want to use it for something else. Use [`Self::bytes_written`] beforehand to
+    /// learn the length of the tree.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] unless exactly one root remains, i.e. every node
+    /// but the last one written has been adopted by a parent. This catches passing the wrong
+    /// number of children to the last call of [`Self::write_node`]. Use [`Self::finish_forest`]
+    /// to write several trees, or none at all. The error hands back the builder, so the writer is
+    /// not lost, see [`FinishError::into_builder`].
+    pub fn finish(mut self) -> Result<W, FinishError<N, W>>
+    where
+        W: Write,
+    {
+        if self.open_node_sizes.len() != 1 {
+            let error = io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Expected exactly one root, but {} nodes have no parent.",
+                    self.open_node_sizes.len()
+                ),
+            );
+            return Err(FinishError::new(error, self));
+        }
+        match self.writer.flush() {
+            Ok(()) => Ok(self.writer),
+            Err(error) => Err(FinishError::new(error, self)),
+        }
+    }
+
+    /// Like [`Self::finish`], but allows for any number of roots. The trees are stored back to
+    /// back in the order they have been written.
+    pub fn finish_forest(mut self) -> io::Result<W>
     where
         W: Write,
     {
         self.writer.flush()?;
         Ok(self.writer)
     }
+
+    /// Total number of bytes written so far, including size headers.
+    pub fn bytes_written(&self) -> TreeSize {
+        self.bytes_written
+    }
+}
+
+impl<N> TreeBuilder<N, Vec<u8>> {
+    /// Builds a tree in memory, into a buffer with capacity for at least `num_bytes`. Also
+    /// reserves room for `num_open_nodes`, see [`Self::reserve`]. Does not change the output.
+    pub fn with_capacity(num_bytes: usize, num_open_nodes: usize) -> Self {
+        let mut builder = Self::new(Vec::with_capacity(num_bytes));
+        builder.reserve(num_open_nodes);
+        builder
+    }
+
+    /// Like [`Self::write_node`], but fails with [`io::ErrorKind::InvalidInput`] if two of the
+    /// adopted children have equal root values. Nothing is written in that case. Useful for
+    /// keyed trees, where sibling keys must be distinct. Compares each pair of children, so this
+    /// is quadratic in `num_children`.
+    pub fn write_node_unique(&mut self, value: &N::Value, num_children: usize) -> io::Result<()>
+    where
+        N: Node,
+        N::Value: PartialEq,
+    {
+        if let Some(first_child) = self.open_node_sizes.len().checked_sub(num_children) {
+            // Children are the last subtrees written. Locate them from the back.
+            let mut end = self.writer.len();
+            let mut siblings = Vec::with_capacity(num_children);
+            for &size in self.open_node_sizes[first_child..].iter().rev() {
+                let start = end - size as usize;
+                siblings.push(
+                    TreeSlice::<N>::from_slice(&self.writer[start..end])
+                        .read_node()
+                        .0,
+                );
+                end = start;
+            }
+            for (index, sibling) in siblings.iter().enumerate() {
+                if siblings[(index + 1)..].contains(sibling) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Siblings must not have equal root values.",
+                    ));
+                }
+            }
+        }
+        self.write_node(value, num_children)
+    }
+
+    /// Finishes building a tree in memory, like [`Self::finish`]. Returns the tree together with
+    /// the byte range of each subtree within it, indexed by the pre-order index of its root node.
+    /// Children are ordered the way they have been written. Ranges instead of plain offsets are
+    /// returned, since a node and its first written child start at the same offset. The ranges
+    /// are recorded while writing, which must be enabled with [`Self::with_index`].
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if the index has not been enabled before writing
+    /// the first node, and like
+    /// [`Self::finish`] unless exactly one root remains.
+    pub fn finish_with_index(mut self) -> io::Result<(TreeVec<N>, Vec<Range<usize>>)>
+    where
+        N: Node,
+    {
+        let Some(mut ranges) = self.index.take() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Recording the index must be enabled with `with_index` before writing nodes.",
+            ));
+        };
+        let bytes = self.finish()?;
+        // Ranges have been recorded in post-order. In pre-order each node precedes its
+        // descendants, which start at the same offset or later and end earlier.
+        ranges.sort_unstable_by_key(|range| (range.start, Reverse(range.end)));
+        Ok((TreeVec::new(bytes), ranges))
+    }
 }
 
 /// An owned tree, which is stored in contigious memory. Fast traversal and query times.
 pub struct TreeVec<N> {
     _node_type: PhantomData<N>,
     bytes: Vec<u8>,
+    /// Number of trailing bytes in `bytes`, which do not belong to the tree. E.g. a checksum
+    /// appended by [`TreeBuilder::finish_with_crc_footer`].
+    footer_len: usize,
 }
 
 impl<N> TreeVec<N> {
     /// Takes ownership of the bytes, and interprets them as a tree. No checks are performed wether
     /// these actually describe a sensible tree. None of Rusts safety guarantees are violated if
     /// providing 'random' bytes in this constructor. For bugfree code utilizing bytes written with
-    /// [`TreeBuilder`] is recommended, though.
+    /// [`TreeBuilder`] is recommended, though. Use [`Self::try_new`] for bytes from untrusted
+    /// sources.
     pub fn new(bytes: Vec<u8>) -> TreeVec<N> {
         TreeVec {
             _node_type: PhantomData,
             bytes,
+            footer_len: 0,
+        }
+    }
+
+    /// Like [`Self::new`], but checks the layout of the bytes first, using
+    /// [`TreeSlice::validate`].
+    pub fn try_new(bytes: Vec<u8>) -> Result<TreeVec<N>, TreeError>
+    where
+        N: Node,
+    {
+        TreeSlice::<N>::from_slice(&bytes).validate()?;
+        Ok(TreeVec::new(bytes))
+    }
+
+    /// Reassembles a tree from the two blobs returned by [`TreeSlice::to_columnar`]. The
+    /// reassembled tree is checked with [`TreeSlice::validate`], so blobs which do not describe a
+    /// tree are rejected right away, rather than causing panics while reading.
+    pub fn from_columnar(values: &[u8], structure: &[u8]) -> Result<TreeVec<N>, TreeError>
+    where
+        N: Node,
+    {
+        const ENTRY_SIZE: usize = 2 * TREE_SIZE_SIZE;
+        if !structure.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(TreeError::Malformed {
+                reason: "Length of structure must be a multiple of 16.",
+            });
+        }
+        let mut bytes = Vec::with_capacity(values.len() + structure.len() / 2);
+        let mut remaining_values = values;
+        for entry in structure.chunks(ENTRY_SIZE) {
+            let (value_len, tree_size) = entry.split_at(TREE_SIZE_SIZE);
+            let value_len = TreeSize::from_le_bytes(value_len.try_into().unwrap()) as usize;
+            if value_len > remaining_values.len() {
+                return Err(TreeError::Malformed {
+                    reason: "Structure references more value bytes than present.",
+                });
+            }
+            let (value, rest) = remaining_values.split_at(value_len);
+            bytes.extend_from_slice(value);
+            bytes.extend_from_slice(tree_size);
+            remaining_values = rest;
         }
+        if !remaining_values.is_empty() {
+            return Err(TreeError::Malformed {
+                reason: "Value bytes not referenced by structure.",
+            });
+        }
+        TreeVec::try_new(bytes)
+    }
+
+    /// Builds a balanced binary search tree from values sorted in ascending order. Each node has
+    /// the median of its range as value, the smaller values as its first written child and the
+    /// larger ones as its second. Of the two candidates for the median of an even number of values
+    /// the larger one is chosen. Therefore a node with only one child always has a left child
+    /// holding the smaller values.
+    ///
+    /// An empty slice results in an empty buffer, which does not contain a tree to read.
+    pub fn from_sorted(sorted: &[N::Value]) -> io::Result<TreeVec<N>>
+    where
+        N: Node,
+    {
+        fn write_range<N: Node>(
+            builder: &mut TreeBuilder<N, Vec<u8>>,
+            range: &[N::Value],
+        ) -> io::Result<()> {
+            // Recursion depth is logarithmic in the number of values, so no need for an explicit
+            // stack.
+            let median = range.len() / 2;
+            let (left, right) = (&range[..median], &range[(median + 1)..]);
+            let mut num_children = 0;
+            for side in [left, right] {
+                if !side.is_empty() {
+                    write_range(builder, side)?;
+                    num_children += 1;
+                }
+            }
+            builder.write_node(&range[median], num_children)
+        }
+
+        let mut builder = TreeBuilder::<N, _>::new(Vec::new());
+        if !sorted.is_empty() {
+            write_range(&mut builder, sorted)?;
+        }
+        Ok(TreeVec::new(builder.finish_forest()?))
+    }
+
+    /// Builds a segment tree over `leaves`. Each inner node covers a range of leaves, which is
+    /// split in halves between its two children. Its value is `combine` applied to the values of
+    /// its first (left) and second (right) child. For an odd number of leaves the left half is the
+    /// smaller one.
+    ///
+    /// An empty slice results in an empty buffer, which does not contain a tree to read.
+    pub fn segment_tree(
+        leaves: &[N::Value],
+        combine: impl Fn(&N::Value, &N::Value) -> N::Value,
+    ) -> io::Result<TreeVec<N>>
+    where
+        N: Node,
+    {
+        /// Writes the subtree covering `leaves` and returns the value of its root, if it is not a
+        /// leaf itself.
+        fn write_range<N: Node>(
+            builder: &mut TreeBuilder<N, Vec<u8>>,
+            leaves: &[N::Value],
+            combine: &impl Fn(&N::Value, &N::Value) -> N::Value,
+        ) -> io::Result<Option<N::Value>> {
+            // Recursion depth is logarithmic in the number of leaves, so no need for an explicit
+            // stack.
+            if leaves.len() == 1 {
+                builder.write_node(&leaves[0], 0)?;
+                return Ok(None);
+            }
+            let (left, right) = leaves.split_at(leaves.len() / 2);
+            let left_value = write_range(builder, left, combine)?;
+            let right_value = write_range(builder, right, combine)?;
+            let value = combine(
+                left_value.as_ref().unwrap_or(&left[0]),
+                right_value.as_ref().unwrap_or(&right[0]),
+            );
+            builder.write_node(&value, 2)?;
+            Ok(Some(value))
+        }
+
+        let mut builder = TreeBuilder::<N, _>::new(Vec::new());
+        if !leaves.is_empty() {
+            write_range(&mut builder, leaves, &combine)?;
+        }
+        Ok(TreeVec::new(builder.finish_forest()?))
     }
 
     pub fn as_tree_slice(&self) -> &TreeSlice<N> {
-        TreeSlice::from_slice(&self.bytes)
+        TreeSlice::from_slice(&self.bytes[..(self.bytes.len() - self.footer_len)])
+    }
+
+    /// Yields an owned copy of every subtree in pre-order, i.e. each node before its children and
+    /// children in the order they have been written. The first item is a copy of the entire tree.
+    /// Trades memory for the convenience of not having to deal with borrowed [`TreeSlice`]s.
+    pub fn into_subtrees_dfs(self) -> impl Iterator<Item = TreeVec<N>>
+    where
+        N: Node,
+    {
+        let ranges: Vec<_> = self
+            .pre_order()
+            .map(|subtree| self.offset_of(subtree)..(self.offset_of(subtree) + subtree.bytes.len()))
+            .collect();
+        ranges
+            .into_iter()
+            .map(move |range| TreeVec::new(self.bytes[range].to_vec()))
     }
 }
 
@@ -127,6 +690,25 @@ impl<N> Deref for TreeVec<N> {
     }
 }
 
+/// Compares logical trees rather than bytes, see the implementation for [`TreeSlice`].
+impl<N, M> PartialEq<TreeVec<M>> for TreeVec<N>
+where
+    N: Node,
+    M: Node,
+    N::Value: PartialEq<M::Value>,
+{
+    fn eq(&self, other: &TreeVec<M>) -> bool {
+        self.as_tree_slice() == other.as_tree_slice()
+    }
+}
+
+impl<N> Eq for TreeVec<N>
+where
+    N: Node,
+    N::Value: Eq,
+{
+}
+
 /// Each subtree is contigious in memory and can borrowed independently similarly to a slice of
 /// bytes.
 pub struct TreeSlice<N> {
@@ -140,8 +722,124 @@ impl<N> TreeSlice<N> {
         unsafe { &*(ptr as *const TreeSlice<N>) }
     }
 
+    /// Number of bytes occupied by this subtree. Includes the trailing size header of its root, as
+    /// well as the values and size headers of all its descendants.
+    pub fn byte_len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Binary representation of this subtree. It is a tree in its own right, so it can be copied
+    /// and wrapped with e.g. [`TreeVec::new`] in order to persist just this subtree.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Borrows the tree occupying `range` of `bytes`, e.g. one tree of a forest stored in a larger
+    /// buffer, without copying it. Checks that the range is within `bytes` and that the size
+    /// header at its end spans the entire range. Descendants are not validated.
+    ///
+    /// Returns [`TreeError::Malformed`] if any of these checks fail.
+    pub fn slice_range(bytes: &[u8], range: Range<usize>) -> Result<&Self, TreeError>
+    where
+        N: Node,
+    {
+        let slice = bytes.get(range).ok_or(TreeError::Malformed {
+            reason: "Range exceeds the bounds of the buffer.",
+        })?;
+        if slice.len() < TREE_SIZE_SIZE
+            || read_size::<N>(slice) != (slice.len() - TREE_SIZE_SIZE) as TreeSize
+        {
+            return Err(TreeError::Malformed {
+                reason: "Size header at the end of the range does not span the range.",
+            });
+        }
+        Ok(Self::from_slice(slice))
+    }
+
+    /// `true` if the size header of the root spans all bytes of the slice, i.e. they hold exactly
+    /// one tree. `false` e.g. for a forest written by [`TreeBuilder::finish_forest`], of which
+    /// [`Self::read_node`] would only see the last root, or for truncated bytes. Only reads the
+    /// size header of the root. Use [`Self::validate`] to check the descendants, too.
+    pub fn is_single_rooted(&self) -> bool
+    where
+        N: Node,
+    {
+        self.bytes.len() >= TREE_SIZE_SIZE
+            && read_size::<N>(&self.bytes) == (self.bytes.len() - TREE_SIZE_SIZE) as TreeSize
+    }
+
+    /// Walks the entire tree once and checks that every size header stays within the bounds of
+    /// its parent, and that the root spans all bytes of the slice. Afterwards traversing the tree
+    /// does not panic due to out of bounds size headers. Use this to check trees from untrusted
+    /// sources borrowed via [`Self::from_slice`].
+    ///
+    /// Returns [`TreeError::SizeOutOfBounds`] for the first size header claiming more bytes than
+    /// are available and [`TreeError::Malformed`] for other violations, e.g. a subtree too small
+    /// to hold its value. Values are decoded with [`Node::try_read_value`], so this does not panic
+    /// for node types implementing it.
+    pub fn validate(&self) -> Result<(), TreeError>
+    where
+        N: Node,
+    {
+        let total_size = self.bytes.len();
+        if total_size < TREE_SIZE_SIZE {
+            return Err(TreeError::Malformed {
+                reason: "Tree is too small to hold a size header.",
+            });
+        }
+        let root_size = read_size::<N>(&self.bytes);
+        let remaining = total_size - TREE_SIZE_SIZE;
+        if root_size > remaining as TreeSize {
+            return Err(TreeError::SizeOutOfBounds {
+                offset: remaining,
+                claimed: root_size,
+                remaining,
+            });
+        }
+        if root_size != remaining as TreeSize {
+            return Err(TreeError::Malformed {
+                reason: "Root does not span all bytes of the tree.",
+            });
+        }
+        let mut stack = vec![self];
+        while let Some(subtree) = stack.pop() {
+            let total_size = subtree.bytes.len();
+            let Some((size_value, _)) =
+                N::try_read_value(&subtree.bytes[..(total_size - TREE_SIZE_SIZE)])
+            else {
+                return Err(TreeError::Malformed {
+                    reason: "Subtree does not hold a valid value.",
+                });
+            };
+            // Bytes of the children not visited yet. Like `Branches` we peel them off the back.
+            let mut children = &subtree.bytes[..(total_size - TREE_SIZE_SIZE - size_value)];
+            while !children.is_empty() {
+                if children.len() < TREE_SIZE_SIZE {
+                    return Err(TreeError::Malformed {
+                        reason: "Branch is too small to hold a size header.",
+                    });
+                }
+                let claimed = read_size::<N>(children);
+                let remaining = children.len() - TREE_SIZE_SIZE;
+                if claimed > remaining as TreeSize {
+                    let start = children.as_ptr() as usize - self.bytes.as_ptr() as usize;
+                    return Err(TreeError::SizeOutOfBounds {
+                        offset: start + remaining,
+                        claimed,
+                        remaining,
+                    });
+                }
+                let (rest, child) = children.split_at(remaining - claimed as usize);
+                stack.push(TreeSlice::from_slice(child));
+                children = rest;
+            }
+        }
+        Ok(())
+    }
+
     /// Deserializes the value of the root node of this silce, and returns an iterator over its
-    /// children.
+    /// children. The children are yielded in **reverse** order, starting with the last one
+    /// written. See [`Self::read_node_ordered`] for the order they have been written in.
     pub fn read_node(&self) -> (N::Value, Branches<'_, N>)
     where
         N: Node,
@@ -151,82 +849,1633 @@ impl<N> TreeSlice<N> {
         let branches = Branches {
             _node_type: PhantomData,
             bytes: &self.bytes[..(total_size - TREE_SIZE_SIZE - size_value)],
+            len: None,
         };
         (value, branches)
     }
-}
 
-/// Iterates over the individual root nodes of subtrees
-pub struct Branches<'a, N> {
-    _node_type: PhantomData<N>,
-    bytes: &'a [u8],
-}
+    /// Like [`Self::read_node`], but borrows the value from the bytes of this slice instead of
+    /// deserializing an owned one. See [`BorrowingNode`].
+    pub fn read_node_borrowed(&self) -> (N::Borrowed<'_>, Branches<'_, N>)
+    where
+        N: BorrowingNode,
+    {
+        let total_size = self.bytes.len();
+        let (size_value, value) = N::read_borrowed(&self.bytes[..(total_size - TREE_SIZE_SIZE)]);
+        let branches = Branches {
+            _node_type: PhantomData,
+            bytes: &self.bytes[..(total_size - TREE_SIZE_SIZE - size_value)],
+            len: None,
+        };
+        (value, branches)
+    }
 
-impl<'a, N: 'a> Iterator for Branches<'a, N> {
-    type Item = &'a TreeSlice<N>;
+    /// Like [`Self::read_node`], but yields the children in the order they have been written, so
+    /// the first child yielded is the first one passed to [`TreeBuilder::write_node`]. Since size
+    /// headers are stored at the end of each subtree, the boundaries of all children are located
+    /// upfront, which requires an allocation.
+    pub fn read_node_ordered(&self) -> (N::Value, OrderedBranches<'_, N>)
+    where
+        N: Node,
+    {
+        let (value, branches) = self.read_node();
+        let mut children: Vec<_> = branches.collect();
+        children.reverse();
+        let branches = OrderedBranches {
+            children: children.into_iter(),
+        };
+        (value, branches)
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.bytes.is_empty() {
-            None
-        } else {
-            let total_size = self.bytes.len();
-            let tree_size_bytes: &[u8; TREE_SIZE_SIZE] = self.bytes
-                [(total_size - TREE_SIZE_SIZE)..]
-                .try_into()
-                .unwrap();
-            let tree_size = TreeSize::from_le_bytes(*tree_size_bytes) as usize;
-            let (remainder, tree_slice) =
-                self.bytes.split_at(total_size - tree_size - TREE_SIZE_SIZE);
-            let tree_slice = TreeSlice::from_slice(tree_slice);
+    /// The child of the root written last. `None` for a leaf. Cheap, since the last written child
+    /// is located directly in front of the value of its parent, so the other children are not
+    /// visited.
+    pub fn last_child(&self) -> Option<&TreeSlice<N>>
+    where
+        N: Node,
+    {
+        self.read_node().1.next()
+    }
 
-            // Advance iterator by assigning all bytes **not** part of the tree slice just returned.
-            self.bytes = remainder;
+    /// Appends the binary representation of this subtree to `buf`, e.g. to extract many subtrees
+    /// without allocating for each of them. `buf` is not cleared beforehand.
+    pub fn clone_into_buf(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.bytes);
+    }
 
-            Some(tree_slice)
+    /// Iterates over the values of all nodes together with their depth. The root is at depth `0`.
+    /// The traversal is pre-order, i.e. each node is visited before its descendants, and children
+    /// are visited in the order they have been written. Uses an explicit stack, so deep trees do
+    /// not overflow the call stack.
+    pub fn iter_depth_first(&self) -> DepthFirstIter<'_, N> {
+        DepthFirstIter { walk: self.walk() }
+    }
+
+    /// Sends the values of all nodes to `sender` in pre-order, children in the order they have
+    /// been written. Blocks while the channel is full, so values are decoded no faster than the
+    /// receiving end consumes them. Stops and returns the value which could not be delivered, once
+    /// the receiver has been dropped.
+    pub fn stream_values_to(&self, sender: SyncSender<N::Value>) -> Result<(), SendError<N::Value>>
+    where
+        N: Node,
+    {
+        for value in self.values_pre_order() {
+            sender.send(value)?;
         }
+        Ok(())
     }
-}
 
-/// 32 Bit signed integer stored in little endian byte order
-pub struct LeI32;
+    /// The child of the root at position `index` in the order children have been written, i.e.
+    /// `0` is the first child passed to [`TreeBuilder::write_node`]. `None` if the root has fewer
+    /// children. Scans the size headers of the children up to twice: once to count them, and
+    /// once more from the last written child up to the one requested.
+    pub fn child(&self, index: usize) -> Option<&TreeSlice<N>>
+    where
+        N: Node,
+    {
+        let mut branches = self.read_node().1;
+        // Branches are stored in reverse order
+        let reverse_index = branches.count().checked_sub(index + 1)?;
+        branches.nth(reverse_index)
+    }
 
-impl Node for LeI32 {
-    type Value = i32;
+    /// The subtree reached by following `path` from the root. Each element selects a child by its
+    /// index, like [`Self::child`] does. An empty path yields `self`. `None` if any index is out of
+    /// range. Does not allocate.
+    pub fn navigate(&self, path: &[usize]) -> Option<&TreeSlice<N>>
+    where
+        N: Node,
+    {
+        path.iter()
+            .try_fold(self, |subtree, &index| subtree.child(index))
+    }
 
-    fn write_value<W>(writer: &mut W, value: &Self::Value) -> std::io::Result<usize>
+    /// For each node with children, in pre-order, its direct children in the order they have been
+    /// written. Leaves are skipped, so no group is empty.
+    pub fn sibling_groups(&self) -> impl Iterator<Item = Vec<&TreeSlice<N>>> + '_
     where
-        W: Write,
+        N: Node,
     {
-        let bytes = value.to_le_bytes();
-        writer.write_all(&bytes)?;
-        Ok(bytes.len()) // Should always be 4
+        self.pre_order()
+            .map(|subtree| subtree.children())
+            .filter(|children| !children.is_empty())
     }
 
-    fn read_value(bytes: &[u8]) -> (usize, i32) {
-        let total_len = bytes.len();
-        let last_four_bytes: &[u8; 4] = bytes[(total_len - 4)..].try_into().unwrap();
-        (4, i32::from_le_bytes(*last_four_bytes))
+    /// Number of direct children of the root. Counts the branches [`Self::read_node`] would
+    /// yield, by scanning their size headers.
+    pub fn num_children(&self) -> usize
+    where
+        N: Node,
+    {
+        self.read_node().1.count()
     }
-}
 
-/// 8 Bit unsigned integer stored in little endian byte order
-pub struct U8;
+    /// `true` if the root has no children. Cheaper than [`Self::num_children`], since no size
+    /// headers of children need to be read.
+    pub fn is_leaf(&self) -> bool
+    where
+        N: Node,
+    {
+        self.bytes.len() == TREE_SIZE_SIZE + self.value_size()
+    }
 
-impl Node for U8 {
-    type Value = u8;
+    /// A cheap heuristic for how similar the shapes of two trees are. Values are ignored. The
+    /// score is computed by comparing the number of nodes on each level of both trees, it is `1.0`
+    /// for trees with identical level sizes and approaches `0.0` the more they differ. This is
+    /// **not** a tree edit distance. Different trees with the same number of nodes per level are
+    /// indistinguishable to it.
+    pub fn shape_similarity(&self, other: &TreeSlice<N>) -> f64
+    where
+        N: Node,
+    {
+        let lhs = self.level_widths();
+        let rhs = other.level_widths();
+        let mut shared = 0;
+        let mut total = 0;
+        for level in 0..lhs.len().max(rhs.len()) {
+            let left = lhs.get(level).copied().unwrap_or(0);
+            let right = rhs.get(level).copied().unwrap_or(0);
+            shared += left.min(right);
+            total += left.max(right);
+        }
+        shared as f64 / total as f64
+    }
 
-    fn write_value<W>(writer: &mut W, value: &Self::Value) -> std::io::Result<usize>
+    /// Number of levels, starting from the root, on which both trees are identical in structure
+    /// and values. Children are compared in the order they have been written. `0` if the roots
+    /// differ, and the height of the trees if they are equal. Compares the trees level by level,
+    /// so it stops at the first level which differs.
+    pub fn common_prefix_depth(&self, other: &TreeSlice<N>) -> usize
     where
-        W: Write,
+        N: Node,
+        N::Value: PartialEq,
+    {
+        let mut depth = 0;
+        let mut lhs = vec![self];
+        let mut rhs = vec![other];
+        loop {
+            let values_equal = lhs.len() == rhs.len()
+                && lhs
+                    .iter()
+                    .zip(&rhs)
+                    .all(|(left, right)| left.read_node().0 == right.read_node().0);
+            if !values_equal {
+                return depth;
+            }
+            depth += 1;
+            let mut next_lhs = Vec::new();
+            let mut next_rhs = Vec::new();
+            for (left, right) in lhs.into_iter().zip(rhs) {
+                let left_children = left.children();
+                let right_children = right.children();
+                if left_children.len() != right_children.len() {
+                    return depth;
+                }
+                next_lhs.extend(left_children);
+                next_rhs.extend(right_children);
+            }
+            if next_lhs.is_empty() {
+                return depth;
+            }
+            lhs = next_lhs;
+            rhs = next_rhs;
+        }
+    }
+
+    /// Rebuilds the tree, replacing the values of all nodes matching `predicate` with the result of
+    /// `replacement`. The structure of the tree remains unchanged.
+    pub fn replace_where<F, G>(&self, predicate: F, replacement: G) -> io::Result<TreeVec<N>>
+    where
+        N: Node,
+        F: Fn(&N::Value) -> bool,
+        G: Fn(N::Value) -> N::Value,
+    {
+        self.rebuild(|value, children| {
+            let value = if predicate(&value) {
+                replacement(value)
+            } else {
+                value
+            };
+            (value, children)
+        })
+    }
+
+    /// `true` if both trees have the same shape and `value_rel` holds for the values of each pair
+    /// of corresponding nodes. The trees may use different [`Node`] types, which allows validating
+    /// transformations between them.
+    pub fn is_isomorphic<M, F>(&self, other: &TreeSlice<M>, value_rel: F) -> bool
+    where
+        N: Node,
+        M: Node,
+        F: Fn(&N::Value, &M::Value) -> bool,
+    {
+        let mut stack = vec![(self, other)];
+        while let Some((lhs, rhs)) = stack.pop() {
+            let (lhs_value, mut lhs_branches) = lhs.read_node();
+            let (rhs_value, mut rhs_branches) = rhs.read_node();
+            if !value_rel(&lhs_value, &rhs_value) {
+                return false;
+            }
+            loop {
+                match (lhs_branches.next(), rhs_branches.next()) {
+                    (Some(lhs_child), Some(rhs_child)) => stack.push((lhs_child, rhs_child)),
+                    (None, None) => break,
+                    // Different number of children
+                    _ => return false,
+                }
+            }
+        }
+        true
+    }
+
+    /// Given the offset at which a subtree starts, returns the offset one past its last byte. So
+    /// `start..end` can be used to slice the subtree out of the bytes of `self`.
+    ///
+    /// Subtrees are delimited by their trailing size header, so a node and its first written child
+    /// start at the same offset. In this case the end of the outermost subtree is returned.
+    pub fn subtree_end_offset(&self, start: usize) -> Result<usize, TreeError>
+    where
+        N: Node,
+    {
+        let mut subtree = self;
+        loop {
+            let subtree_start = self.offset_of(subtree);
+            if subtree_start == start {
+                return Ok(subtree_start + subtree.bytes.len());
+            }
+            subtree = subtree
+                .read_node()
+                .1
+                .find(|child| {
+                    let child_start = self.offset_of(child);
+                    child_start <= start && start < child_start + child.bytes.len()
+                })
+                .ok_or(TreeError::NoSubtreeAt { offset: start })?;
+        }
+    }
+
+    /// Splits the tree into two blobs. The first one holds the serialized values of all nodes, the
+    /// second one the structure of the tree. Storing them separately may benefit compression, as
+    /// similar data is grouped together. Use [`TreeVec::from_columnar`] to reassemble the tree.
+    ///
+    /// Nodes are stored in the same order in both blobs, which is the order they have been
+    /// written. For each node the structure contains two [`TreeSize`]s in little endian, the
+    /// length of the value in bytes, followed by the size header of the node.
+    pub fn to_columnar(&self) -> (Vec<u8>, Vec<u8>)
+    where
+        N: Node,
+    {
+        let mut ends: Vec<_> = self
+            .pre_order()
+            .map(|subtree| (self.end_of(subtree), subtree.value_size()))
+            .collect();
+        // The value and size of each node are stored at the end of its subtree. Sorting by the end
+        // offsets yields the nodes in the order they have been written.
+        ends.sort_unstable();
+        let mut values = Vec::new();
+        let mut structure = Vec::with_capacity(ends.len() * 2 * TREE_SIZE_SIZE);
+        for (end, value_len) in ends {
+            let header_start = end - TREE_SIZE_SIZE;
+            values.extend_from_slice(&self.bytes[(header_start - value_len)..header_start]);
+            structure.extend_from_slice(&(value_len as TreeSize).to_le_bytes());
+            structure.extend_from_slice(&self.bytes[header_start..end]);
+        }
+        (values, structure)
+    }
+
+    /// Value of the node at `preorder_index`, counting nodes in pre-order with children in the
+    /// order they have been written. Index `0` is the root. `None` if the tree has fewer nodes.
+    /// Stops traversing the tree once the node is found.
+    pub fn value_at(&self, preorder_index: usize) -> Option<N::Value>
+    where
+        N: Node,
     {
-        let bytes = value.to_le_bytes();
-        writer.write_all(&bytes)?;
-        Ok(bytes.len()) // Should always be 1
+        self.values_pre_order().nth(preorder_index)
+    }
+
+    /// Writes the values of all nodes into `out` in pre-order, with children in the order they
+    /// have been written. Returns the number of nodes. Avoids allocating a collection for the
+    /// values, e.g. if the number of nodes is known upfront.
+    ///
+    /// Returns [`TreeError::BufferTooSmall`] if `out` can not hold all values. Its elements may
+    /// have been overwritten in that case.
+    pub fn read_values_into(&self, out: &mut [N::Value]) -> Result<usize, TreeError>
+    where
+        N: Node,
+    {
+        let mut count = 0;
+        for value in self.values_pre_order() {
+            let Some(slot) = out.get_mut(count) else {
+                return Err(TreeError::BufferTooSmall {
+                    capacity: out.len(),
+                    required: self.pre_order().count(),
+                });
+            };
+            *slot = value;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Histogram of the sizes in bytes of all subtrees, including their size headers. Element `i`
+    /// counts the subtrees with a size in `(i * bucket)..((i + 1) * bucket)`. The last element
+    /// holds the bucket of the largest subtree, which is the entire tree.
+    ///
+    /// # Panics
+    ///
+    /// If `bucket` is `0`.
+    pub fn subtree_size_histogram(&self, bucket: usize) -> Vec<usize>
+    where
+        N: Node,
+    {
+        assert!(bucket != 0, "Bucket width must not be zero.");
+        let mut histogram = vec![0; self.bytes.len() / bucket + 1];
+        for subtree in self.pre_order() {
+            histogram[subtree.bytes.len() / bucket] += 1;
+        }
+        histogram
+    }
+
+    /// Streams the canonical form of the tree into `out`. In the canonical form the children of
+    /// each node are sorted, so trees which only differ in the order of their children have the
+    /// same canonical form. No intermediate [`TreeVec`] is built.
+    ///
+    /// Children are ordered by comparing the sequences of values and child counts of their own
+    /// canonical forms in pre-order. These sequences are held in memory for every subtree during
+    /// the write, which requires memory proportional to the number of nodes times the height of the
+    /// tree.
+    pub fn write_canonical<W>(&self, out: &mut W) -> io::Result<()>
+    where
+        N: Node,
+        N::Value: Ord + Clone,
+        W: Write,
+    {
+        // Canonical pre-order sequence of each subtree, keyed by the offset where the subtree ends.
+        let mut keys: HashMap<usize, Vec<(N::Value, usize)>> = HashMap::new();
+        let subtrees: Vec<_> = self.pre_order().collect();
+        // Reverse pre-order visits every child before its parent.
+        for &subtree in subtrees.iter().rev() {
+            let (value, branches) = subtree.read_node();
+            let mut child_keys: Vec<_> = branches.map(|child| &keys[&self.end_of(child)]).collect();
+            child_keys.sort_unstable();
+            let mut key = vec![(value, child_keys.len())];
+            for child_key in child_keys {
+                key.extend_from_slice(child_key);
+            }
+            keys.insert(self.end_of(subtree), key);
+        }
+        self.rebuild_into(out, |value, mut children| {
+            children.sort_by(|lhs, rhs| keys[&self.end_of(lhs)].cmp(&keys[&self.end_of(rhs)]));
+            (value, children)
+        })?;
+        Ok(())
+    }
+
+    /// Number of distinct values among all nodes of the tree.
+    pub fn distinct_value_count(&self) -> usize
+    where
+        N: Node,
+        N::Value: Eq + Hash,
+    {
+        self.values_pre_order().collect::<HashSet<_>>().len()
+    }
+
+    /// Counts how often each value occurs among the nodes of the tree.
+    pub fn value_frequencies(&self) -> HashMap<N::Value, usize>
+    where
+        N: Node,
+        N::Value: Eq + Hash + Clone,
+    {
+        let mut frequencies = HashMap::new();
+        for value in self.values_pre_order() {
+            *frequencies.entry(value).or_insert(0) += 1;
+        }
+        frequencies
+    }
+
+    /// Rebuilds the tree with the order of the children reversed for every node. The first
+    /// written child becomes the last one and vice versa.
+    pub fn reverse_children(&self) -> io::Result<TreeVec<N>>
+    where
+        N: Node,
+    {
+        self.rebuild(|value, mut children| {
+            children.reverse();
+            (value, children)
+        })
+    }
+
+    /// Path from the root to the first node in pre-order whose value matches `predicate`. Each
+    /// element of the path is the index of a child in the order the children have been written.
+    /// An empty path denotes the root itself. `None` if no node matches.
+    pub fn find_path<F>(&self, predicate: F) -> Option<Vec<usize>>
+    where
+        N: Node,
+        F: Fn(&N::Value) -> bool,
+    {
+        let mut path = Vec::new();
+        for visit in self.walk() {
+            // Root has no position, its children are at depth one.
+            if visit.depth > 0 {
+                path.truncate(visit.depth - 1);
+                path.push(visit.position);
+            }
+            if predicate(&visit.value) {
+                return Some(path);
+            }
+        }
+        None
+    }
+
+    /// Sum of the sizes of all serialized values in bytes. This is the size of the tree minus the
+    /// size headers of its nodes.
+    pub fn total_value_bytes(&self) -> usize
+    where
+        N: Node,
+    {
+        self.pre_order().map(TreeSlice::value_size).sum()
+    }
+
+    /// Iterates over the values of a binary tree in-order. For each node the values of the subtree
+    /// of its first written child (left) are yielded, then its own value, followed by the values
+    /// of its second child (right). A single child is treated as the left one. For nodes with
+    /// more than two children, the subtrees of all children after the first one are yielded after
+    /// the node itself, in the order they have been written.
+    pub fn values_inorder(&self) -> impl Iterator<Item = N::Value> + '_
+    where
+        N: Node,
+    {
+        self.in_order().map(|subtree| subtree.read_node().0)
+    }
+
+    /// The subtree rooted at the node following the node at `preorder_index` in the in-order
+    /// traversal described by [`Self::values_inorder`]. Nodes are counted in pre-order with
+    /// children in the order they have been written. `None` for the last node in in-order, or if
+    /// the tree has fewer nodes. In a binary search tree this is the node with the next larger key.
+    pub fn inorder_successor(&self, preorder_index: usize) -> Option<&TreeSlice<N>>
+    where
+        N: Node,
+    {
+        let node = self.pre_order().nth(preorder_index)?;
+        // Comparing fat pointers also compares lengths, so a node is not confused with its first
+        // child, which starts at the same address.
+        self.in_order()
+            .skip_while(|&subtree| !std::ptr::eq(subtree, node))
+            .nth(1)
+    }
+
+    /// All subtrees indexed by the pre-order index of their root node, with children visited in
+    /// the order they have been written. Index `0` is the entire tree. Computing the table takes
+    /// one traversal, afterwards every subtree can be looked up in constant time.
+    pub fn subtree_table(&self) -> Vec<&TreeSlice<N>>
+    where
+        N: Node,
+    {
+        self.pre_order().collect()
+    }
+
+    /// Depth of the shallowest node with two or more children. The root has depth `0`. `None` if
+    /// the tree is a chain, i.e. no node has more than one child. Only descends along the trunk of
+    /// the tree, since above the first branching node each level consists of a single node.
+    pub fn first_branch_depth(&self) -> Option<usize>
+    where
+        N: Node,
+    {
+        let mut subtree = self;
+        let mut depth = 0;
+        loop {
+            let (_, mut branches) = subtree.read_node();
+            let only_child = branches.next()?;
+            if branches.next().is_some() {
+                return Some(depth);
+            }
+            subtree = only_child;
+            depth += 1;
+        }
+    }
+
+    /// Values along the longest path starting at the root and descending towards the leaves, along
+    /// which the values are strictly increasing. Children with a value equal to or smaller than
+    /// their parent end the path. The path does not need to end in a leaf, and always contains at
+    /// least the root. If several paths have the same length, the first one in pre-order is
+    /// returned, i.e. the one through the earlier written children.
+    pub fn longest_increasing_path(&self) -> Vec<N::Value>
+    where
+        N: Node,
+        N::Value: PartialOrd + Clone,
+    {
+        let mut best = Vec::new();
+        let mut path: Vec<N::Value> = Vec::new();
+        let mut walk = self.walk();
+        while let Some(visit) = walk.next() {
+            // Only children of nodes on an increasing path are visited, so `path` holds all
+            // ancestors.
+            path.truncate(visit.depth);
+            if path.last().is_some_and(|parent| visit.value <= *parent) {
+                walk.skip_children();
+                continue;
+            }
+            path.push(visit.value);
+            if path.len() > best.len() {
+                best = path.clone();
+            }
+        }
+        best
+    }
+
+    /// Rebuilds the tree keeping only the first `max` written children of each node. The other
+    /// children are dropped along with their subtrees.
+    pub fn limit_children(&self, max: usize) -> io::Result<TreeVec<N>>
+    where
+        N: Node,
+    {
+        self.rebuild(|value, mut children| {
+            children.truncate(max);
+            (value, children)
+        })
+    }
+
+    /// Compares two trees considering only the nodes whose values satisfy `keep`. A node which is
+    /// not kept is removed from its tree, its children take its place among the children of its
+    /// parent, in the order they have been written. If the root itself is not kept, the tree
+    /// collapses into a forest of its kept descendants. The two trees are equal if their
+    /// collapsed forests are equal, i.e. have the same number of trees with the same values and
+    /// the same children in the same order.
+    pub fn eq_filtered<F>(&self, other: &TreeSlice<N>, keep: F) -> bool
+    where
+        N: Node,
+        N::Value: PartialEq,
+        F: Fn(&N::Value) -> bool,
+    {
+        self.collapse(&keep) == other.collapse(&keep)
+    }
+
+    /// Pre-order sequence of values and number of children of the forest remaining, if all nodes
+    /// not satisfying `keep` are removed and their children take their place. Together the values
+    /// and child counts describe the forest unambiguously.
+    fn collapse<F>(&self, keep: F) -> Vec<(N::Value, usize)>
+    where
+        N: Node,
+        F: Fn(&N::Value) -> bool,
+    {
+        let mut collapsed: Vec<(N::Value, usize)> = Vec::new();
+        // For each node visited so far, the index of the closest kept node in `collapsed` among
+        // itself and its ancestors.
+        let mut kept_ancestors: Vec<Option<usize>> = Vec::new();
+        for visit in self.walk() {
+            let kept_ancestor = visit.parent.and_then(|parent| kept_ancestors[parent]);
+            if keep(&visit.value) {
+                if let Some(ancestor) = kept_ancestor {
+                    collapsed[ancestor].1 += 1;
+                }
+                collapsed.push((visit.value, 0));
+                kept_ancestors.push(Some(collapsed.len() - 1));
+            } else {
+                kept_ancestors.push(kept_ancestor);
+            }
+        }
+        collapsed
+    }
+
+    /// Adler-32 checksum of the binary representation of the tree. Cheap to compute and useful to
+    /// detect whether a tree changed. Not suitable to protect against deliberate tampering.
+    pub fn adler32(&self) -> u32 {
+        const MOD_ADLER: u32 = 65521;
+        // Largest number of bytes which can be summed up before `b` could overflow a `u32`.
+        const CHUNK_SIZE: usize = 5552;
+        let mut a: u32 = 1;
+        let mut b: u32 = 0;
+        for chunk in self.bytes.chunks(CHUNK_SIZE) {
+            for &byte in chunk {
+                a += byte as u32;
+                b += a;
+            }
+            a %= MOD_ADLER;
+            b %= MOD_ADLER;
+        }
+        (b << 16) | a
+    }
+
+    /// All subtrees grouped by the value of their root node. Within each group subtrees are
+    /// ordered by the pre-order index of their root, with children in the order they have been
+    /// written.
+    pub fn group_by_root(&self) -> HashMap<N::Value, Vec<&TreeSlice<N>>>
+    where
+        N: Node,
+        N::Value: Eq + Hash,
+    {
+        let mut groups: HashMap<N::Value, Vec<&TreeSlice<N>>> = HashMap::new();
+        for subtree in self.pre_order() {
+            groups
+                .entry(subtree.read_node().0)
+                .or_default()
+                .push(subtree);
+        }
+        groups
+    }
+
+    /// Smallest and largest value among all nodes. `None` if the slice is empty and does not hold
+    /// any node, like the trees built from an empty slice by [`TreeVec::from_sorted`].
+    pub fn value_bounds(&self) -> Option<(N::Value, N::Value)>
+    where
+        N: Node,
+        N::Value: Ord + Clone,
+    {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        let mut values = self.values_pre_order();
+        let first = values.next()?;
+        let bounds = values.fold((first.clone(), first), |(min, max), value| {
+            if value < min {
+                (value, max)
+            } else if value > max {
+                (min, value)
+            } else {
+                (min, max)
+            }
+        });
+        Some(bounds)
+    }
+
+    /// Groups the start offsets of subtrees whose binary representations are identical. Only groups
+    /// with at least two subtrees are returned. Offsets within a group are ascending, and groups are
+    /// ordered by their first offset. If two subtrees are identical, so are their descendants, so
+    /// these show up as groups of their own.
+    pub fn duplicate_subtrees(&self) -> Vec<Vec<usize>>
+    where
+        N: Node,
+    {
+        let mut by_bytes: HashMap<&[u8], Vec<usize>> = HashMap::new();
+        for subtree in self.pre_order() {
+            by_bytes
+                .entry(&subtree.bytes)
+                .or_default()
+                .push(self.offset_of(subtree));
+        }
+        let mut groups: Vec<_> = by_bytes
+            .into_values()
+            .filter(|offsets| offsets.len() > 1)
+            .map(|mut offsets| {
+                offsets.sort_unstable();
+                offsets
+            })
+            .collect();
+        groups.sort_unstable();
+        groups
+    }
+
+    /// Number of bytes saved, if every subtree identical to one already stored would be replaced
+    /// by a reference to it. Subtrees within a replaced subtree are not counted again. The costs
+    /// of the references themselves are not accounted for. See also [`Self::duplicate_subtrees`].
+    pub fn dedup_savings(&self) -> usize
+    where
+        N: Node,
+    {
+        let mut seen = HashSet::new();
+        let mut savings = 0;
+        let mut stack = vec![self];
+        while let Some(subtree) = stack.pop() {
+            if seen.insert(&subtree.bytes) {
+                stack.extend(subtree.read_node().1);
+            } else {
+                savings += subtree.bytes.len();
+            }
+        }
+        savings
+    }
+
+    /// Rebuilds the tree with the subtree rooted at `preorder_index` replaced by a copy of
+    /// `new_subtree`. Nodes are counted in pre-order with children in the order they have been
+    /// written. Index `0` replaces the entire tree. The size headers of all ancestors are
+    /// recomputed. Fails with [`io::ErrorKind::InvalidInput`] if the tree has fewer nodes.
+    pub fn replace_subtree(
+        &self,
+        preorder_index: usize,
+        new_subtree: &TreeSlice<N>,
+    ) -> io::Result<TreeVec<N>>
+    where
+        N: Node,
+    {
+        let target = self.pre_order().nth(preorder_index).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Pre-order index of subtree to replace is out of range.",
+            )
+        })?;
+        if preorder_index == 0 {
+            return Ok(TreeVec::new(new_subtree.bytes.to_vec()));
+        }
+        self.rebuild(|value, mut children| {
+            // Comparing fat pointers also compares lengths, so a node is not confused with its
+            // first child, which starts at the same address.
+            if let Some(child) = children
+                .iter_mut()
+                .find(|child| std::ptr::eq(**child, target))
+            {
+                *child = new_subtree;
+            }
+            (value, children)
+        })
+    }
+
+    /// Every pair of values `(ancestor, descendant)`, where the first node lies on the path from
+    /// the root to the second one. Not just direct parent child relations. Descendants are
+    /// visited in pre-order, and for each of them its ancestors starting with the root. Yields
+    /// `O(depth * nodes)` pairs.
+    pub fn ancestor_descendant_pairs(&self) -> impl Iterator<Item = (N::Value, N::Value)> + '_
+    where
+        N: Node,
+        N::Value: Clone,
+    {
+        // Values and end offsets of the nodes on the path from the root to the current node.
+        let mut path: Vec<(N::Value, usize)> = Vec::new();
+        self.pre_order().flat_map(move |subtree| {
+            let end = self.end_of(subtree);
+            // Subtrees visited earlier, which are not ancestors, end before this one.
+            while path
+                .last()
+                .is_some_and(|&(_, ancestor_end)| ancestor_end < end)
+            {
+                path.pop();
+            }
+            let value = subtree.read_node().0;
+            let pairs: Vec<_> = path
+                .iter()
+                .map(|(ancestor, _)| (ancestor.clone(), value.clone()))
+                .collect();
+            path.push((value, end));
+            pairs
+        })
+    }
+
+    /// Copy of this tree, which keeps only the nodes on the paths from the root to the selected
+    /// leaves. Leaves are indexed in pre-order, i.e. from the first written to the last written
+    /// one, starting with `0`. Every other subtree is dropped. The root is always kept, so an
+    /// empty selection yields just the root.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if an index is not smaller than the number of
+    /// leaves.
+    pub fn minimal_cover(&self, leaf_indices: &[usize]) -> io::Result<TreeVec<N>>
+    where
+        N: Node,
+    {
+        // End offsets of all nodes to keep.
+        let mut keep = HashSet::new();
+        // End offsets of the nodes on the path from the root to the current node.
+        let mut path = Vec::new();
+        let mut num_leaves = 0;
+        for subtree in self.pre_order() {
+            let end = self.end_of(subtree);
+            // Subtrees visited earlier, which are not ancestors, end before this one.
+            while path.last().is_some_and(|&ancestor_end| ancestor_end < end) {
+                path.pop();
+            }
+            path.push(end);
+            if subtree.is_leaf() {
+                if leaf_indices.contains(&num_leaves) {
+                    keep.extend(path.iter().copied());
+                }
+                num_leaves += 1;
+            }
+        }
+        if leaf_indices.iter().any(|&index| index >= num_leaves) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Leaf index is out of range.",
+            ));
+        }
+        self.rebuild(|value, mut children| {
+            children.retain(|&child| keep.contains(&self.end_of(child)));
+            (value, children)
+        })
+    }
+
+    /// Computes a result for each node from its value and the results of its children, and returns
+    /// the result of the root. Children are evaluated first and passed to `f` in the order they
+    /// have been written. E.g. evaluates an expression tree, or sums up the weights of each
+    /// subtree. Uses an explicit stack, so deep trees do not overflow the call stack.
+    pub fn fold<'a, B>(&'a self, mut f: impl FnMut(N::Value, Vec<B>) -> B) -> B
+    where
+        N: Node,
+    {
+        /// A node, whose children are still being folded.
+        struct Frame<'a, N: Node> {
+            value: N::Value,
+            /// Children not folded yet. Next child to fold on top.
+            pending: Vec<&'a TreeSlice<N>>,
+            /// Position of the result of its first child in `results`.
+            first_result: usize,
+        }
+
+        // Results of subtrees, which have not been passed to their parent yet.
+        let mut results = Vec::new();
+        let visit = |subtree: &'a TreeSlice<N>, first_result| {
+            let (value, branches) = subtree.read_node();
+            Frame {
+                value,
+                pending: branches.collect(),
+                first_result,
+            }
+        };
+        let mut stack = vec![visit(self, 0)];
+        while let Some(frame) = stack.last_mut() {
+            if let Some(child) = frame.pending.pop() {
+                stack.push(visit(child, results.len()));
+            } else {
+                let frame = stack.pop().unwrap();
+                let children = results.split_off(frame.first_result);
+                results.push(f(frame.value, children));
+            }
+        }
+        results.pop().unwrap()
+    }
+
+    /// Writes a new tree in post-order. For each node `transform` is called with its value and its
+    /// children in the order they have been written. It returns the value to write instead and the
+    /// children to write beneath it. Implemented with an explicit stack, in order not to overflow
+    /// the call stack for deep trees.
+    fn rebuild<'a, F>(&'a self, transform: F) -> io::Result<TreeVec<N>>
+    where
+        N: Node,
+        F: FnMut(N::Value, Vec<&'a TreeSlice<N>>) -> (N::Value, Vec<&'a TreeSlice<N>>),
+    {
+        let bytes = self.rebuild_into(Vec::new(), transform)?;
+        Ok(TreeVec::new(bytes))
+    }
+
+    /// Like [`Self::rebuild`], but streams the new tree into `writer`, which is returned once the
+    /// tree is complete.
+    fn rebuild_into<'a, W, F>(&'a self, writer: W, mut transform: F) -> io::Result<W>
+    where
+        N: Node,
+        W: Write,
+        F: FnMut(N::Value, Vec<&'a TreeSlice<N>>) -> (N::Value, Vec<&'a TreeSlice<N>>),
+    {
+        /// A node, whose value has been transformed, but which is still waiting for its children
+        /// to be written.
+        struct Frame<'a, N: Node> {
+            value: N::Value,
+            num_children: usize,
+            /// Children not written yet. Next child to write on top.
+            pending: Vec<&'a TreeSlice<N>>,
+        }
+
+        let mut visit = |subtree: &'a TreeSlice<N>| {
+            let (value, children) = transform(subtree.read_node().0, subtree.children());
+            Frame::<N> {
+                value,
+                num_children: children.len(),
+                pending: children.into_iter().rev().collect(),
+            }
+        };
+
+        let mut builder = TreeBuilder::<N, _>::new(writer);
+        let mut stack = vec![visit(self)];
+        while let Some(frame) = stack.last_mut() {
+            if let Some(child) = frame.pending.pop() {
+                stack.push(visit(child));
+            } else {
+                let frame = stack.pop().unwrap();
+                builder.write_node(&frame.value, frame.num_children)?;
+            }
+        }
+        Ok(builder.finish()?)
+    }
+
+    /// Direct children of the root node, in the order they have been written.
+    fn children(&self) -> Vec<&TreeSlice<N>>
+    where
+        N: Node,
+    {
+        self.read_node_ordered().1.collect()
+    }
+
+    /// Size of the serialized value of the root node in bytes.
+    fn value_size(&self) -> usize
+    where
+        N: Node,
+    {
+        N::read_value(&self.bytes[..(self.bytes.len() - TREE_SIZE_SIZE)]).0
+    }
+
+    /// Values of all nodes in pre-order.
+    fn values_pre_order(&self) -> impl Iterator<Item = N::Value> + '_
+    where
+        N: Node,
+    {
+        self.walk().map(|visit| visit.value)
+    }
+
+    /// Iterates over all subtrees in pre-order. Children are visited in the order they have been
+    /// written.
+    fn pre_order(&self) -> impl Iterator<Item = &TreeSlice<N>> + '_
+    where
+        N: Node,
+    {
+        self.walk().map(|visit| visit.subtree)
+    }
+
+    /// Like [`Self::pre_order`], but also tells the depth, parent and position among siblings of
+    /// each node, see [`Visit`].
+    fn walk(&self) -> Walk<'_, N> {
+        Walk {
+            stack: vec![(self, 0, None, 0)],
+            next_index: 0,
+            num_children: 0,
+        }
+    }
+
+    /// Iterates over all subtrees in the in-order described by [`Self::values_inorder`].
+    fn in_order(&self) -> InOrder<'_, N>
+    where
+        N: Node,
+    {
+        InOrder {
+            stack: vec![InOrderStep::Visit(self)],
+        }
+    }
+
+    /// Position of a subtree of `self` relative to the start of `self`.
+    fn offset_of(&self, subtree: &TreeSlice<N>) -> usize {
+        subtree.bytes.as_ptr() as usize - self.bytes.as_ptr() as usize
+    }
+
+    /// Position one past the last byte of a subtree of `self` relative to the start of `self`.
+    /// Contrary to the start, the end offset is unique for each subtree.
+    fn end_of(&self, subtree: &TreeSlice<N>) -> usize {
+        self.offset_of(subtree) + subtree.bytes.len()
+    }
+
+    /// Number of nodes on each level of the tree, starting with the root level.
+    fn level_widths(&self) -> Vec<usize>
+    where
+        N: Node,
+    {
+        let mut widths = Vec::new();
+        let mut level = vec![self];
+        while !level.is_empty() {
+            widths.push(level.len());
+            level = level
+                .into_iter()
+                .flat_map(|node| node.read_node().1)
+                .collect();
+        }
+        widths
+    }
+}
+
+/// Pre-order traversal over all subtrees, which keeps track of the depth, parent and position of
+/// each node. Children are visited in the order they have been written. Implemented with an
+/// explicit stack in order to not overflow the call stack for deep trees. Shared by all pre-order
+/// traversals of a trusted tree.
+struct Walk<'a, N> {
+    /// Subtrees still to visit, together with their depth, the pre-order index of their parent and
+    /// their position among their siblings. Next subtree to visit on top.
+    stack: Vec<(&'a TreeSlice<N>, usize, Option<usize>, usize)>,
+    /// Pre-order index of the next node to visit.
+    next_index: usize,
+    /// Number of children of the node visited last, which are on top of the stack.
+    num_children: usize,
+}
+
+/// A node visited by [`Walk`].
+struct Visit<'a, N: Node> {
+    subtree: &'a TreeSlice<N>,
+    value: N::Value,
+    /// Position of the node in pre-order, not counting skipped nodes.
+    index: usize,
+    /// Number of edges between the root and the node, i.e. `0` for the root.
+    depth: usize,
+    /// Pre-order index of the parent, `None` for the root.
+    parent: Option<usize>,
+    /// Index among its siblings in the order they have been written, `0` for the root.
+    position: usize,
+    num_children: usize,
+}
+
+impl<N> Walk<'_, N> {
+    /// Descendants of the node visited last are not visited. Its children are still counted in
+    /// [`Visit::num_children`].
+    fn skip_children(&mut self) {
+        self.stack.truncate(self.stack.len() - self.num_children);
+        self.num_children = 0;
+    }
+}
+
+impl<'a, N> Iterator for Walk<'a, N>
+where
+    N: Node + 'a,
+{
+    type Item = Visit<'a, N>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (subtree, depth, parent, position) = self.stack.pop()?;
+        let (value, branches) = subtree.read_node();
+        let index = self.next_index;
+        self.next_index += 1;
+        let first_child = self.stack.len();
+        // Branches yields the last written child first, so the first written child ends up on top
+        // of the stack.
+        self.stack
+            .extend(branches.map(|child| (child, depth + 1, Some(index), 0)));
+        self.num_children = self.stack.len() - first_child;
+        for (reverse_position, pending) in self.stack[first_child..].iter_mut().enumerate() {
+            pending.3 = self.num_children - 1 - reverse_position;
+        }
+        Some(Visit {
+            subtree,
+            value,
+            index,
+            depth,
+            parent,
+            position,
+            num_children: self.num_children,
+        })
+    }
+}
+
+/// Two trees are equal if their roots have equal values and their children are equal pairwise, in
+/// the same order. The binary representations are not compared, so trees written with different
+/// [`Node`] types, e.g. [`LeI32`] and [`BeI32`], may be equal. See [`TreeSlice::is_isomorphic`]
+/// for other relations between values.
+impl<N, M> PartialEq<TreeSlice<M>> for TreeSlice<N>
+where
+    N: Node,
+    M: Node,
+    N::Value: PartialEq<M::Value>,
+{
+    fn eq(&self, other: &TreeSlice<M>) -> bool {
+        self.is_isomorphic(other, |lhs, rhs| lhs == rhs)
+    }
+}
+
+impl<N> Eq for TreeSlice<N>
+where
+    N: Node,
+    N::Value: Eq,
+{
+}
+
+/// Pre-order traversal over the values of a tree and their depths, see
+/// [`TreeSlice::iter_depth_first`].
+pub struct DepthFirstIter<'a, N> {
+    walk: Walk<'a, N>,
+}
+
+impl<'a, N> Iterator for DepthFirstIter<'a, N>
+where
+    N: Node + 'a,
+{
+    type Item = (usize, N::Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.walk.next().map(|visit| (visit.depth, visit.value))
+    }
+}
+
+/// In-order traversal over the subtrees of a tree, see [`TreeSlice::values_inorder`].
+struct InOrder<'a, N: Node> {
+    stack: Vec<InOrderStep<'a, N>>,
+}
+
+enum InOrderStep<'a, N: Node> {
+    /// Subtree which has not been read yet
+    Visit(&'a TreeSlice<N>),
+    /// Subtree whose left subtree has already been yielded
+    Emit(&'a TreeSlice<N>),
+}
+
+impl<'a, N> Iterator for InOrder<'a, N>
+where
+    N: Node + 'a,
+{
+    type Item = &'a TreeSlice<N>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.pop()? {
+                InOrderStep::Emit(subtree) => return Some(subtree),
+                InOrderStep::Visit(subtree) => {
+                    // Children arrive starting with the last one written, so they can be pushed
+                    // onto the stack in this order.
+                    let mut children: Vec<_> = subtree.read_node().1.collect();
+                    let left = children.pop();
+                    self.stack
+                        .extend(children.into_iter().map(InOrderStep::Visit));
+                    self.stack.push(InOrderStep::Emit(subtree));
+                    if let Some(left) = left {
+                        self.stack.push(InOrderStep::Visit(left));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Iterates over the individual root nodes of subtrees. Children are yielded in reverse order,
+/// starting with the last one written, since each step peels off the subtree at the back of the
+/// remaining bytes. See [`OrderedBranches`] for the order they have been written in.
+///
+/// Iterating from the back via [`DoubleEndedIterator`] yields the children in the order they have
+/// been written. Since size headers are located at the end of each subtree, each step from the
+/// back has to scan all remaining branches, so prefer [`TreeSlice::read_node_ordered`] to visit
+/// all of them in written order. Likewise [`ExactSizeIterator::len`] scans the remaining branches
+/// once, unless their number is already known from a step taken from the back.
+pub struct Branches<'a, N> {
+    _node_type: PhantomData<N>,
+    bytes: &'a [u8],
+    /// Number of remaining branches, once it has been counted.
+    len: Option<usize>,
+}
+
+impl<'a, N> Branches<'a, N> {
+    /// Binary representation of the branches not yielded yet, i.e. the children of the node in
+    /// the order they have been written. Shrinks from the back with each call to
+    /// [`Iterator::next`] and from the front with each call to [`DoubleEndedIterator::next_back`].
+    pub fn remaining_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+}
+
+// Implemented manually, since deriving would require `N: Clone`. Copies are independent cursors,
+// which continue from the same position.
+impl<N> Clone for Branches<'_, N> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<N> Copy for Branches<'_, N> {}
+
+impl<'a, N> Branches<'a, N>
+where
+    N: Node,
+{
+    /// Like [`Iterator::next`], but checks that the size header of the next branch is within the
+    /// bounds of the remaining bytes and that the branch holds a valid value, so
+    /// [`TreeSlice::read_node`] does not panic for it. Returns [`TreeError::SizeOutOfBounds`] or
+    /// [`TreeError::Malformed`] instead of panicking, like [`skip_subtree`] and
+    /// [`Node::try_read_value`]. Use this to traverse trees from untrusted sources. After an error
+    /// the iterator is exhausted.
+    ///
+    /// Every call consumes at least the size header of the branch returned, so iteration always
+    /// terminates, even if the headers claim empty subtrees.
+    pub fn checked_next(&mut self) -> Option<Result<&'a TreeSlice<N>, TreeError>> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        let result = skip_subtree::<N>(self.bytes).and_then(|remainder| {
+            let (remainder, tree_slice) = self.bytes.split_at(remainder.len());
+            let value_bytes = &tree_slice[..(tree_slice.len() - TREE_SIZE_SIZE)];
+            if N::try_read_value(value_bytes).is_none() {
+                return Err(TreeError::Malformed {
+                    reason: "Branch does not hold a valid value.",
+                });
+            }
+            self.bytes = remainder;
+            self.len = self.len.map(|len| len - 1);
+            Ok(TreeSlice::from_slice(tree_slice))
+        });
+        if result.is_err() {
+            self.bytes = &[];
+            self.len = Some(0);
+        }
+        Some(result)
+    }
+
+    /// Splits the remaining bytes into the bytes in front of the last branch and the last branch.
+    fn split_last(bytes: &'a [u8]) -> (&'a [u8], &'a [u8]) {
+        let total_size = bytes.len();
+        let tree_size = read_size::<N>(bytes) as usize;
+        bytes.split_at(total_size - tree_size - TREE_SIZE_SIZE)
+    }
+
+    /// Counts the remaining branches, by peeling them off the back one by one.
+    fn count_remaining(&self) -> usize {
+        let mut bytes = self.bytes;
+        let mut count = 0;
+        while !bytes.is_empty() {
+            bytes = Self::split_last(bytes).0;
+            count += 1;
+        }
+        count
+    }
+}
+
+impl<'a, N> Iterator for Branches<'a, N>
+where
+    N: Node + 'a,
+{
+    type Item = &'a TreeSlice<N>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            None
+        } else {
+            let (remainder, tree_slice) = Self::split_last(self.bytes);
+            let tree_slice = TreeSlice::from_slice(tree_slice);
+
+            // Advance iterator by assigning all bytes **not** part of the tree slice just returned.
+            self.bytes = remainder;
+            self.len = self.len.map(|len| len - 1);
+
+            Some(tree_slice)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len.unwrap_or_else(|| self.count_remaining());
+        (len, Some(len))
+    }
+
+    fn count(self) -> usize {
+        self.len.unwrap_or_else(|| self.count_remaining())
+    }
+}
+
+impl<'a, N> DoubleEndedIterator for Branches<'a, N>
+where
+    N: Node + 'a,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        // The first written branch is the one left, once all others are peeled off the back. The
+        // scan counts the remaining branches as a by-product.
+        let mut front = self.bytes;
+        let mut len = 0;
+        loop {
+            len += 1;
+            let (remainder, tree_slice) = Self::split_last(front);
+            if remainder.is_empty() {
+                front = tree_slice;
+                break;
+            }
+            front = remainder;
+        }
+        self.bytes = &self.bytes[front.len()..];
+        self.len = Some(len - 1);
+        Some(TreeSlice::from_slice(front))
+    }
+}
+
+impl<'a, N> ExactSizeIterator for Branches<'a, N> where N: Node + 'a {}
+
+/// Iterates over the individual root nodes of subtrees in the order they have been written. See
+/// [`TreeSlice::read_node_ordered`].
+pub struct OrderedBranches<'a, N> {
+    children: std::vec::IntoIter<&'a TreeSlice<N>>,
+}
+
+impl<'a, N> Iterator for OrderedBranches<'a, N> {
+    type Item = &'a TreeSlice<N>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.children.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.children.size_hint()
+    }
+}
+
+impl<N> DoubleEndedIterator for OrderedBranches<'_, N> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.children.next_back()
+    }
+}
+
+impl<N> ExactSizeIterator for OrderedBranches<'_, N> {}
+
+/// Defines a [`Node`] type for a fixed width integer in either `little` or `big` endian byte
+/// order. Adding a new combination of width and byte order takes a single line:
+///
+/// ```
+/// use contigious_tree::{impl_int_node, Node};
+///
+/// impl_int_node!(
+///     /// 16 Bit unsigned integer stored in little endian byte order
+///     LeU16, u16, little
+/// );
+///
+/// let mut bytes = Vec::new();
+/// assert_eq!(2, LeU16::write_value(&mut bytes, &0x0102).unwrap());
+/// assert_eq!(vec![2, 1], bytes);
+/// assert_eq!((2, 0x0102), LeU16::read_value(&bytes));
+/// ```
+#[macro_export]
+macro_rules! impl_int_node {
+    ($(#[$doc:meta])* $name:ident, $int:ty, little) => {
+        $crate::impl_int_node!(@impl $(#[$doc])* $name, $int, to_le_bytes, from_le_bytes);
+    };
+    ($(#[$doc:meta])* $name:ident, $int:ty, big) => {
+        $crate::impl_int_node!(@impl $(#[$doc])* $name, $int, to_be_bytes, from_be_bytes);
+    };
+    (@impl $(#[$doc:meta])* $name:ident, $int:ty, $to_bytes:ident, $from_bytes:ident) => {
+        $(#[$doc])*
+        pub struct $name;
+
+        impl $crate::Node for $name {
+            type Value = $int;
+
+            fn write_value<W>(writer: &mut W, value: &Self::Value) -> ::std::io::Result<usize>
+            where
+                W: ::std::io::Write,
+            {
+                let bytes = value.$to_bytes();
+                writer.write_all(&bytes)?;
+                Ok(bytes.len())
+            }
+
+            fn read_value(bytes: &[u8]) -> (usize, $int) {
+                const SIZE: usize = ::std::mem::size_of::<$int>();
+                let total_len = bytes.len();
+                let last_bytes: [u8; SIZE] = bytes[(total_len - SIZE)..].try_into().unwrap();
+                (SIZE, <$int>::$from_bytes(last_bytes))
+            }
+
+            fn try_read_value(bytes: &[u8]) -> ::std::option::Option<(usize, $int)> {
+                const SIZE: usize = ::std::mem::size_of::<$int>();
+                (bytes.len() >= SIZE).then(|| Self::read_value(bytes))
+            }
+        }
+    };
+}
+
+impl_int_node!(
+    /// 32 Bit signed integer stored in little endian byte order
+    LeI32, i32, little
+);
+impl_int_node!(
+    /// 8 Bit unsigned integer stored in little endian byte order
+    U8, u8, little
+);
+impl_int_node!(
+    /// 32 Bit signed integer stored in big endian byte order
+    BeI32, i32, big
+);
+impl_int_node!(
+    /// 64 Bit signed integer stored in big endian byte order
+    BeI64, i64, big
+);
+impl_int_node!(
+    /// 32 Bit unsigned integer stored in big endian byte order
+    BeU32, u32, big
+);
+impl_int_node!(
+    /// 64 Bit unsigned integer stored in big endian byte order
+    BeU64, u64, big
+);
+
+/// Boolean stored as a single byte. `0` is `false`, anything else is `true`. See
+/// [`PackedBoolBuilder`] for a more compact representation of trees with many boolean leaves.
+pub struct Bool;
+
+impl Node for Bool {
+    type Value = bool;
+
+    fn write_value<W>(writer: &mut W, value: &Self::Value) -> std::io::Result<usize>
+    where
+        W: Write,
+    {
+        writer.write_all(&[*value as u8])?;
+        Ok(1)
+    }
+
+    fn read_value(bytes: &[u8]) -> (usize, bool) {
+        (1, bytes[bytes.len() - 1] != 0)
+    }
+
+    fn try_read_value(bytes: &[u8]) -> Option<(usize, bool)> {
+        bytes.last().map(|&byte| (1, byte != 0))
+    }
+}
+
+/// Unsigned 64 Bit integer stored as LEB128 varint, using one byte for each 7 bits of the value.
+/// Compact for small values, which take a single byte for `0..128`. The bytes are stored in
+/// reverse order, starting with the most significant group of bits. This way reading from the
+/// back, all but the last byte read have their continuation bit set, and the boundary to the bytes
+/// in front of the value is unambiguous.
+pub struct VarU64;
+
+impl Node for VarU64 {
+    type Value = u64;
+
+    fn write_value<W>(writer: &mut W, value: &Self::Value) -> io::Result<usize>
+    where
+        W: Write,
+    {
+        const CONTINUATION: u8 = 0x80;
+        // At most 10 groups of 7 bits are needed for 64 bits
+        let mut groups = [0u8; 10];
+        let mut len = 0;
+        let mut remaining = *value;
+        loop {
+            groups[len] = (remaining & 0x7F) as u8 | CONTINUATION;
+            len += 1;
+            remaining >>= 7;
+            if remaining == 0 {
+                break;
+            }
+        }
+        // The most significant group terminates the varint.
+        groups[len - 1] &= !CONTINUATION;
+        groups[..len].reverse();
+        writer.write_all(&groups[..len])?;
+        Ok(len)
+    }
+
+    fn read_value(bytes: &[u8]) -> (usize, u64) {
+        VarU64::decode(bytes).expect("Value must be a varint of at most 10 bytes.")
+    }
+
+    fn try_read_value(bytes: &[u8]) -> Option<(usize, u64)> {
+        VarU64::decode(bytes)
+    }
+}
+
+impl VarU64 {
+    /// Decodes the varint at the end of `bytes`. `None` if it is not terminated within the 10
+    /// bytes needed for 64 bits, or if its bits do not fit into 64 bits.
+    fn decode(bytes: &[u8]) -> Option<(usize, u64)> {
+        const MAX_LEN: usize = 10;
+        let mut value = 0;
+        for (len, &byte) in bytes.iter().rev().take(MAX_LEN).enumerate() {
+            let group = (byte & 0x7F) as u64;
+            // The tenth group holds only the most significant bit.
+            if len == MAX_LEN - 1 && group > 1 {
+                return None;
+            }
+            value |= group << (7 * len);
+            if byte & 0x80 == 0 {
+                return Some((len + 1, value));
+            }
+        }
+        None
+    }
+}
+
+/// Signed 64 Bit integer stored as zigzag encoded [`VarU64`]. Values close to zero take few bytes,
+/// regardless of their sign. E.g. `-64..64` is stored in a single byte.
+pub struct VarI64;
+
+impl Node for VarI64 {
+    type Value = i64;
+
+    fn write_value<W>(writer: &mut W, value: &Self::Value) -> io::Result<usize>
+    where
+        W: Write,
+    {
+        let zigzag = ((*value << 1) ^ (*value >> 63)) as u64;
+        VarU64::write_value(writer, &zigzag)
+    }
+
+    fn read_value(bytes: &[u8]) -> (usize, i64) {
+        let (len, zigzag) = VarU64::read_value(bytes);
+        (len, VarI64::unzigzag(zigzag))
+    }
+
+    fn try_read_value(bytes: &[u8]) -> Option<(usize, i64)> {
+        let (len, zigzag) = VarU64::decode(bytes)?;
+        Some((len, VarI64::unzigzag(zigzag)))
+    }
+}
+
+impl VarI64 {
+    fn unzigzag(zigzag: u64) -> i64 {
+        (zigzag >> 1) as i64 ^ -((zigzag & 1) as i64)
+    }
+}
+
+/// UTF-8 encoded string of variable length. Serves as an example of how to implement a [`Node`] with
+/// values of variable length: Since values are read from the back, the length of the encoded
+/// string is stored behind it as a little endian [`TreeSize`]. The bytes of the string are located
+/// in front of the length.
+pub struct Utf8;
+
+impl Utf8 {
+    /// Like [`Node::read_value`], but borrows the string from `bytes` instead of allocating.
+    ///
+    /// # Panics
+    ///
+    /// If `bytes` are too short to hold the string or the string is not valid UTF-8.
+    pub fn read_str(bytes: &[u8]) -> (usize, &str) {
+        let (size, text) = Bytes::read_borrowed(bytes);
+        let text = std::str::from_utf8(text).expect("Value must be valid UTF-8");
+        (size, text)
+    }
+
+    /// Like [`Self::read_str`], but returns `None` instead of panicking.
+    fn try_read_str(bytes: &[u8]) -> Option<(usize, &str)> {
+        let (size, text) = Bytes::try_read_bytes(bytes)?;
+        Some((size, std::str::from_utf8(text).ok()?))
+    }
+}
+
+impl Node for Utf8 {
+    type Value = String;
+
+    fn write_value<W>(writer: &mut W, value: &Self::Value) -> io::Result<usize>
+    where
+        W: Write,
+    {
+        writer.write_all(value.as_bytes())?;
+        writer.write_all(&(value.len() as TreeSize).to_le_bytes())?;
+        Ok(value.len() + TREE_SIZE_SIZE)
+    }
+
+    fn read_value(bytes: &[u8]) -> (usize, String) {
+        let (size, text) = Self::read_str(bytes);
+        (size, text.to_owned())
+    }
+
+    fn try_read_value(bytes: &[u8]) -> Option<(usize, String)> {
+        let (size, text) = Self::try_read_str(bytes)?;
+        Some((size, text.to_owned()))
+    }
+}
+
+impl BorrowingNode for Utf8 {
+    type Borrowed<'a> = &'a str;
+
+    fn read_borrowed(bytes: &[u8]) -> (usize, &str) {
+        Self::read_str(bytes)
+    }
+}
+
+/// Raw bytes of variable length. Stored like [`Utf8`], followed by their length as a little endian
+/// [`TreeSize`]. Use [`TreeSlice::read_node_borrowed`] to read values without copying them.
+pub struct Bytes;
+
+impl Node for Bytes {
+    type Value = Vec<u8>;
+
+    fn write_value<W>(writer: &mut W, value: &Self::Value) -> io::Result<usize>
+    where
+        W: Write,
+    {
+        writer.write_all(value)?;
+        writer.write_all(&(value.len() as TreeSize).to_le_bytes())?;
+        Ok(value.len() + TREE_SIZE_SIZE)
+    }
+
+    fn read_value(bytes: &[u8]) -> (usize, Vec<u8>) {
+        let (size, value) = Self::read_borrowed(bytes);
+        (size, value.to_vec())
+    }
+
+    fn try_read_value(bytes: &[u8]) -> Option<(usize, Vec<u8>)> {
+        let (size, value) = Self::try_read_bytes(bytes)?;
+        Some((size, value.to_vec()))
+    }
+}
+
+impl Bytes {
+    /// Like [`BorrowingNode::read_borrowed`], but returns `None` if `bytes` are too short to hold
+    /// the length or the bytes it claims.
+    fn try_read_bytes(bytes: &[u8]) -> Option<(usize, &[u8])> {
+        let end = bytes.len().checked_sub(TREE_SIZE_SIZE)?;
+        let len = TreeSize::from_le_bytes(bytes[end..].try_into().unwrap());
+        let start = end.checked_sub(usize::try_from(len).ok()?)?;
+        Some((end - start + TREE_SIZE_SIZE, &bytes[start..end]))
+    }
+}
+
+impl BorrowingNode for Bytes {
+    type Borrowed<'a> = &'a [u8];
+
+    fn read_borrowed(bytes: &[u8]) -> (usize, &[u8]) {
+        Self::try_read_bytes(bytes).expect("Value must be preceded by its length.")
+    }
+}
+
+/// Wraps a node type, storing its values the same way, but using big endian for the size headers.
+/// E.g. `TreeBuilder::<BigEndianSizes<LeI32>, _>` writes a tree which can be read by a consumer
+/// expecting big endian size headers.
+pub struct BigEndianSizes<N>(PhantomData<N>);
+
+impl<N> Node for BigEndianSizes<N>
+where
+    N: Node,
+{
+    type Value = N::Value;
+
+    const SIZE_ENDIANNESS: Endianness = Endianness::Big;
+
+    fn write_value<W>(writer: &mut W, value: &Self::Value) -> io::Result<usize>
+    where
+        W: Write,
+    {
+        N::write_value(writer, value)
+    }
+
+    fn read_value(bytes: &[u8]) -> (usize, Self::Value) {
+        N::read_value(bytes)
     }
 
-    fn read_value(bytes: &[u8]) -> (usize, u8) {
-        let total_len = bytes.len();
-        let last_four_bytes: &[u8; 1] = bytes[(total_len - 1)..].try_into().unwrap();
-        (1, u8::from_le_bytes(*last_four_bytes))
+    fn try_read_value(bytes: &[u8]) -> Option<(usize, Self::Value)> {
+        N::try_read_value(bytes)
     }
 }
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use crate::{Node, TreeSlice};
+
+/// Summary of the shape of a tree, see [`TreeSlice::metrics`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TreeMetrics {
+    /// Total number of nodes.
+    pub node_count: usize,
+    /// Number of nodes without children.
+    pub leaf_count: usize,
+    /// Number of nodes on the longest path from the root to a leaf. A single leaf has height `1`.
+    pub height: usize,
+    /// Largest number of nodes on any one level of the tree.
+    pub max_width: usize,
+    /// Average number of children of nodes which are not leaves. `0.0` for a single leaf.
+    pub average_branching_factor: f64,
+}
+
+impl<N> TreeSlice<N> {
+    /// Computes several metrics describing the shape of the tree in a single traversal.
+    pub fn metrics(&self) -> TreeMetrics
+    where
+        N: Node,
+    {
+        let mut node_count = 0;
+        let mut leaf_count = 0;
+        let mut height = 0;
+        let mut max_width = 0;
+        let mut level = vec![self];
+        while !level.is_empty() {
+            height += 1;
+            max_width = max_width.max(level.len());
+            node_count += level.len();
+            let mut next_level = Vec::new();
+            for subtree in level {
+                let num_before = next_level.len();
+                next_level.extend(subtree.read_node().1);
+                if next_level.len() == num_before {
+                    leaf_count += 1;
+                }
+            }
+            level = next_level;
+        }
+        let inner_count = node_count - leaf_count;
+        let average_branching_factor = if inner_count == 0 {
+            0.0
+        } else {
+            // Every node but the root is the child of an inner node.
+            (node_count - 1) as f64 / inner_count as f64
+        };
+        TreeMetrics {
+            node_count,
+            leaf_count,
+            height,
+            max_width,
+            average_branching_factor,
+        }
+    }
+
+    /// Number of nodes at exactly `depth`. The root is at depth `0`. Traverses the tree level by
+    /// level and stops once `depth` is reached. Values are decoded only to learn how many bytes
+    /// they occupy, since the children of a node are stored in front of its value.
+    pub fn nodes_at_depth(&self, depth: usize) -> usize
+    where
+        N: Node,
+    {
+        let mut level = vec![self];
+        for _ in 0..depth {
+            if level.is_empty() {
+                break;
+            }
+            level = level
+                .into_iter()
+                .flat_map(|subtree| subtree.read_node().1)
+                .collect();
+        }
+        level.len()
+    }
+
+    /// Number of nodes on the longest path from the root to a leaf. A single leaf has depth `1`,
+    /// like [`TreeMetrics::height`]. Uses an explicit stack, so deep trees do not overflow the
+    /// call stack. Every value is decoded, since the layout does not store how many bytes a value
+    /// occupies, and the children of a node are located in front of it.
+    pub fn depth(&self) -> usize
+    where
+        N: Node,
+    {
+        self.walk()
+            .map(|visit| visit.depth + 1)
+            .max()
+            .expect("Tree must have a root")
+    }
+
+    /// Total number of nodes, including the root. Cheaper than [`Self::metrics`], since it does not
+    /// track levels. Like [`Self::depth`] it still decodes every value to learn how many bytes it
+    /// occupies.
+    pub fn node_count(&self) -> usize
+    where
+        N: Node,
+    {
+        self.pre_order().count()
+    }
+
+    /// `true` if for every node the heights of the subtrees of its children differ by at most
+    /// `max_diff`. Like in an AVL tree a node with a single child is considered to have an empty
+    /// second subtree of height `0`. So `max_diff = 1` fails for a chain of three nodes.
+    pub fn is_height_balanced(&self, max_diff: usize) -> bool
+    where
+        N: Node,
+    {
+        // Height of each subtree, keyed by the offset where it ends.
+        let mut heights = HashMap::new();
+        let subtrees: Vec<_> = self.pre_order().collect();
+        // Reverse pre-order visits every child before its parent.
+        for &subtree in subtrees.iter().rev() {
+            let child_heights: Vec<usize> = subtree
+                .read_node()
+                .1
+                .map(|child| heights[&self.end_of(child)])
+                .collect();
+            let highest = child_heights.iter().copied().max().unwrap_or(0);
+            let lowest = match child_heights.len() {
+                1 => 0,
+                _ => child_heights.iter().copied().min().unwrap_or(0),
+            };
+            if highest - lowest > max_diff {
+                return false;
+            }
+            heights.insert(self.end_of(subtree), highest + 1);
+        }
+        true
+    }
+
+    /// Number of edges on the longest path between any two nodes of the tree. Its ends are leaves,
+    /// or the root if it has a single child. The path may pass through the root or any other
+    /// inner node, combining the two deepest subtrees of its children. `0` for a single leaf.
+    pub fn diameter(&self) -> usize
+    where
+        N: Node,
+    {
+        // Number of edges on the longest path from each subtree to one of its leaves, keyed by the
+        // offset where it ends.
+        let mut depths = HashMap::new();
+        let mut diameter = 0;
+        let subtrees: Vec<_> = self.pre_order().collect();
+        // Reverse pre-order visits every child before its parent.
+        for &subtree in subtrees.iter().rev() {
+            let (mut deepest, mut second) = (0, 0);
+            for child in subtree.read_node().1 {
+                let depth = depths[&self.end_of(child)] + 1;
+                if depth > deepest {
+                    second = deepest;
+                    deepest = depth;
+                } else if depth > second {
+                    second = depth;
+                }
+            }
+            diameter = diameter.max(deepest + second);
+            depths.insert(self.end_of(subtree), deepest);
+        }
+        diameter
+    }
+
+    /// Compares the size of this representation with an estimate for a pointer based one. Returns
+    /// the size in bytes of this tree, followed by the estimated size of a tree where each node
+    /// holds its value, the number of its children and a pointer to each child. Counts and
+    /// pointers are assumed to take eight bytes each. Overhead for allocations is not accounted
+    /// for.
+    pub fn vs_pointer_layout_bytes(&self) -> (usize, usize)
+    where
+        N: Node,
+    {
+        let mut value_bytes = 0;
+        let mut node_count = 0;
+        for subtree in self.pre_order() {
+            value_bytes += subtree.value_size();
+            node_count += 1;
+        }
+        // Every node but the root is pointed to by its parent.
+        let pointer_bytes = value_bytes + 8 * node_count + 8 * (node_count - 1);
+        (self.bytes.len(), pointer_bytes)
+    }
+}
@@ -0,0 +1,179 @@
+use std::io::{self, Write};
+
+use crate::{Node, TreeBuilder, TreeSlice};
+
+/// Maximum number of boolean leaves, which are packed into a single node.
+const GROUP_SIZE: usize = 8;
+
+/// Node type used by trees written with [`PackedBoolBuilder`]. Each node holds between one and
+/// eight booleans. Nodes written with [`PackedBoolBuilder::write_node`] hold exactly one value. A
+/// node holding more than one value is a group of sibling leaves. The booleans are stored as a bit
+/// field in one byte, followed by one byte holding the number of booleans in the group.
+///
+/// Use [`TreeSlice::read_packed_node`] to read these trees, it unpacks the leaf groups for you.
+pub struct PackedBools;
+
+impl Node for PackedBools {
+    type Value = Vec<bool>;
+
+    fn write_value<W>(writer: &mut W, value: &Self::Value) -> io::Result<usize>
+    where
+        W: Write,
+    {
+        if value.is_empty() || value.len() > GROUP_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "A packed node must hold between one and eight booleans.",
+            ));
+        }
+        let bits = value
+            .iter()
+            .enumerate()
+            .fold(0u8, |bits, (index, &bit)| bits | ((bit as u8) << index));
+        writer.write_all(&[bits, value.len() as u8])?;
+        Ok(2)
+    }
+
+    fn read_value(bytes: &[u8]) -> (usize, Vec<bool>) {
+        Self::try_read_value(bytes).expect("Packed node must hold between one and eight booleans.")
+    }
+
+    fn try_read_value(bytes: &[u8]) -> Option<(usize, Vec<bool>)> {
+        let &[.., bits, len] = bytes else {
+            return None;
+        };
+        let len = len as usize;
+        if len == 0 || len > GROUP_SIZE {
+            return None;
+        }
+        let values = (0..len).map(|index| bits & (1 << index) != 0).collect();
+        Some((2, values))
+    }
+}
+
+/// Builds trees of booleans, packing up to eight sibling leaves into a single node. Compared to
+/// writing each leaf with [`crate::Bool`] this saves most of the per node overhead, which is
+/// dominated by the size header.
+///
+/// In a regular tree each subtree is independent of its siblings. This is no longer true for the
+/// packed leaves, which is why this is a dedicated builder writing a dedicated node type
+/// ([`PackedBools`]). Leaves written together with [`Self::write_leaves`] must also be adopted
+/// together by the same parent.
+pub struct PackedBoolBuilder<W> {
+    builder: TreeBuilder<PackedBools, W>,
+    /// For each subtree written to `builder`, which does not have a parent yet, the number of
+    /// logical nodes it represents. `1` for nodes, and the group size for packed leaves.
+    open_node_counts: Vec<usize>,
+}
+
+impl<W> PackedBoolBuilder<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            builder: TreeBuilder::new(writer),
+            open_node_counts: Vec::new(),
+        }
+    }
+
+    /// Adds sibling leaves to the tree, packing up to eight of them into a single node.
+    pub fn write_leaves(&mut self, values: &[bool]) -> io::Result<()>
+    where
+        W: Write,
+    {
+        for group in values.chunks(GROUP_SIZE) {
+            self.builder.write_node(&group.to_vec(), 0)?;
+            self.open_node_counts.push(group.len());
+        }
+        Ok(())
+    }
+
+    /// Adds a node to the tree.
+    ///
+    /// # Parameters
+    ///
+    /// * `value`: Value associated with the node
+    /// * `num_children`: This node will be the parent node of the last `num_children` nodes written
+    ///   which do not have a parent yet. Each leaf written with [`Self::write_leaves`] counts as a
+    ///   node of its own. It is an error to adopt only some of the leaves of a packed group.
+    pub fn write_node(&mut self, value: bool, num_children: usize) -> io::Result<()>
+    where
+        W: Write,
+    {
+        let mut num_adopted = 0;
+        let mut num_subtrees = 0;
+        while num_adopted < num_children {
+            let count = self
+                .open_node_counts
+                .iter()
+                .rev()
+                .nth(num_subtrees)
+                .copied()
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Not enough open nodes to adopt as children.",
+                    )
+                })?;
+            num_adopted += count;
+            num_subtrees += 1;
+        }
+        if num_adopted != num_children {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Children must not split a group of packed leaves.",
+            ));
+        }
+        self.builder.write_node(&vec![value], num_subtrees)?;
+        self.open_node_counts
+            .truncate(self.open_node_counts.len() - num_subtrees);
+        self.open_node_counts.push(1);
+        Ok(())
+    }
+
+    /// Call this once every node has been written. Flushes the output and returns the inner writer
+    /// in case you want to use it for something else. Fails like [`TreeBuilder::finish`], unless
+    /// exactly one root remains. Each leaf written with [`Self::write_leaves`] counts as a root of
+    /// its own, even if it is packed together with its siblings.
+    pub fn finish(self) -> io::Result<W>
+    where
+        W: Write,
+    {
+        if self.open_node_counts != [1] {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Expected exactly one root, but {} nodes have no parent.",
+                    self.open_node_counts.iter().sum::<usize>()
+                ),
+            ));
+        }
+        Ok(self.builder.finish()?)
+    }
+}
+
+/// A child of a node in a tree written by [`PackedBoolBuilder`].
+#[derive(Clone, Copy)]
+pub enum PackedChild<'a> {
+    /// A node without children.
+    Leaf(bool),
+    /// A node with children, written with [`PackedBoolBuilder::write_node`].
+    Node(&'a TreeSlice<PackedBools>),
+}
+
+impl TreeSlice<PackedBools> {
+    /// Deserializes the value of the root node of this slice and its children, unpacking groups
+    /// of packed leaves. Like [`crate::Branches`] the children are returned starting with the last
+    /// one written.
+    pub fn read_packed_node(&self) -> (bool, Vec<PackedChild<'_>>) {
+        let (value, branches) = self.read_node();
+        let mut children = Vec::new();
+        for branch in branches {
+            let (group, mut grand_children) = branch.read_node();
+            if grand_children.next().is_some() {
+                children.push(PackedChild::Node(branch));
+            } else {
+                children.extend(group.into_iter().rev().map(PackedChild::Leaf));
+            }
+        }
+        (value[0], children)
+    }
+}
@@ -0,0 +1,112 @@
+#[cfg(feature = "allocator_api")]
+use std::alloc::Allocator;
+
+use crate::{Node, TreeBuilder, TreeError, TreeSlice, TreeVec};
+
+impl<N> TreeVec<N> {
+    /// Builds a tree from a parent pointer representation. `parents[i]` is the index of the parent
+    /// of the node with value `values[i]`. The root is its own parent. Children are written in
+    /// the order of their indices.
+    ///
+    /// Returns [`TreeError::InvalidParents`] if the lengths of the arrays differ, an index is out
+    /// of range, there is not exactly one root, or the parents form a cycle.
+    pub fn from_parent_array(values: &[N::Value], parents: &[usize]) -> Result<Self, TreeError>
+    where
+        N: Node,
+    {
+        if values.len() != parents.len() {
+            return Err(invalid("Number of values and parents must be equal."));
+        }
+        let mut roots = Vec::new();
+        let mut children = vec![Vec::new(); values.len()];
+        for (index, &parent) in parents.iter().enumerate() {
+            if parent >= parents.len() {
+                return Err(invalid("Index of parent is out of range."));
+            }
+            if parent == index {
+                roots.push(index);
+            } else {
+                children[parent].push(index);
+            }
+        }
+        let &[root] = roots.as_slice() else {
+            return Err(invalid("There must be exactly one root."));
+        };
+
+        // Post-order traversal. For each node we remember how many of its children have been
+        // written already.
+        let mut builder = TreeBuilder::<N, _>::new(Vec::new());
+        let mut num_written = 0;
+        let mut stack = vec![(root, 0)];
+        while let Some((node, next_child)) = stack.last_mut() {
+            if let Some(&child) = children[*node].get(*next_child) {
+                *next_child += 1;
+                stack.push((child, 0));
+            } else {
+                let node = *node;
+                stack.pop();
+                builder
+                    .write_node(&values[node], children[node].len())
+                    .expect("Writing to Vec must not fail");
+                num_written += 1;
+            }
+        }
+        // Nodes in a cycle are not reachable from the root.
+        if num_written != values.len() {
+            return Err(invalid("Parents must not form a cycle."));
+        }
+        Ok(TreeVec::new(
+            builder.finish().expect("Flushing Vec must not fail"),
+        ))
+    }
+}
+
+impl<N> TreeSlice<N> {
+    /// Parent pointer representation of the tree. Nodes are indexed in pre-order, with children
+    /// in the order they have been written. Returns the values of the nodes and the indices of
+    /// their parents. The root at index `0` is its own parent. Inverse of
+    /// [`TreeVec::from_parent_array`].
+    pub fn to_parent_array(&self) -> (Vec<N::Value>, Vec<usize>)
+    where
+        N: Node,
+    {
+        let mut values = Vec::new();
+        let mut parents = Vec::new();
+        self.visit_parent_array(|value, parent| {
+            values.push(value);
+            parents.push(parent);
+        });
+        (values, parents)
+    }
+
+    /// Like [`Self::to_parent_array`], but allocates the returned vectors with `alloc`.
+    #[cfg(feature = "allocator_api")]
+    pub fn to_parent_array_in<A>(&self, alloc: A) -> (Vec<N::Value, A>, Vec<usize, A>)
+    where
+        N: Node,
+        A: Allocator + Clone,
+    {
+        let mut values = Vec::new_in(alloc.clone());
+        let mut parents = Vec::new_in(alloc);
+        self.visit_parent_array(|value, parent| {
+            values.push(value);
+            parents.push(parent);
+        });
+        (values, parents)
+    }
+
+    /// Calls `f` with the value and the index of the parent of each node in pre-order.
+    fn visit_parent_array(&self, mut f: impl FnMut(N::Value, usize))
+    where
+        N: Node,
+    {
+        for visit in self.walk() {
+            // The root is its own parent.
+            f(visit.value, visit.parent.unwrap_or(0));
+        }
+    }
+}
+
+fn invalid(reason: &'static str) -> TreeError {
+    TreeError::InvalidParents { reason }
+}
@@ -0,0 +1,104 @@
+use std::io;
+
+use crate::{Node, TreeBuilder, TreeSize, TreeSlice, TreeVec};
+
+impl<N> TreeSlice<N> {
+    /// Applies `rule` to every node bottom-up and repeats until a pass leaves the tree unchanged.
+    ///
+    /// `rule` is called with the value of a node and its children in the order they have been
+    /// written. Children have already been rewritten in the same pass. Returning `None` keeps the
+    /// node as it is. Returning `Some((value, children))` replaces the node and its entire
+    /// subtree with a new node holding `value` and adopting `children`. A rule which keeps on
+    /// returning `Some` for the same tree, never terminates.
+    pub fn rewrite_fixpoint<F>(&self, rule: F) -> io::Result<TreeVec<N>>
+    where
+        N: Node,
+        F: Fn(&N::Value, &[&TreeSlice<N>]) -> Option<(N::Value, Vec<TreeVec<N>>)>,
+    {
+        let Some(mut tree) = self.rewrite_bottom_up(&rule)? else {
+            return Ok(TreeVec::new(self.bytes.to_vec()));
+        };
+        while let Some(rewritten) = tree.rewrite_bottom_up(&rule)? {
+            tree = rewritten;
+        }
+        Ok(tree)
+    }
+
+    /// A single bottom-up pass of [`Self::rewrite_fixpoint`]. `None` if `rule` did not change
+    /// any node.
+    fn rewrite_bottom_up<F>(&self, rule: &F) -> io::Result<Option<TreeVec<N>>>
+    where
+        N: Node,
+        F: Fn(&N::Value, &[&TreeSlice<N>]) -> Option<(N::Value, Vec<TreeVec<N>>)>,
+    {
+        let mut changed = false;
+        let mut builder = TreeBuilder::<N, _>::new(Vec::new());
+        // Post-order traversal. For each node we remember the children not written yet, with the
+        // next child to write on top.
+        let mut stack = vec![Frame::new(self)];
+        while let Some(frame) = stack.last_mut() {
+            if let Some(child) = frame.pending.pop() {
+                stack.push(Frame::new(child));
+                continue;
+            }
+            let Frame {
+                value,
+                num_children,
+                ..
+            } = stack.pop().unwrap();
+            // The rewritten children are the last subtrees written to the builder.
+            let first_child = builder.open_node_sizes.len() - num_children;
+            let children_size: TreeSize = builder.open_node_sizes[first_child..].iter().sum();
+            let start = builder.writer.len() - children_size as usize;
+            let replacement = {
+                let mut children = Vec::with_capacity(num_children);
+                let mut end = start;
+                for &size in &builder.open_node_sizes[first_child..] {
+                    let child_start = end;
+                    end += size as usize;
+                    children.push(TreeSlice::<N>::from_slice(
+                        &builder.writer[child_start..end],
+                    ));
+                }
+                rule(&value, &children)
+            };
+            match replacement {
+                Some((new_value, new_children)) => {
+                    changed = true;
+                    builder.writer.truncate(start);
+                    builder.open_node_sizes.truncate(first_child);
+                    for child in &new_children {
+                        builder.write_subtree(child)?;
+                    }
+                    builder.write_node(&new_value, new_children.len())?;
+                }
+                None => builder.write_node(&value, num_children)?,
+            }
+        }
+        if changed {
+            Ok(Some(TreeVec::new(builder.finish()?)))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// A node visited by [`TreeSlice::rewrite_bottom_up`], whose children are still being written.
+struct Frame<'a, N: Node> {
+    value: N::Value,
+    num_children: usize,
+    /// Children not written yet. Next child to write on top.
+    pending: Vec<&'a TreeSlice<N>>,
+}
+
+impl<'a, N: Node> Frame<'a, N> {
+    fn new(subtree: &'a TreeSlice<N>) -> Self {
+        let (value, branches) = subtree.read_node();
+        let pending: Vec<_> = branches.collect();
+        Frame {
+            value,
+            num_children: pending.len(),
+            pending,
+        }
+    }
+}
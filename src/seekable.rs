@@ -0,0 +1,185 @@
+use std::{
+    io::{self, Read, Seek, SeekFrom},
+    marker::PhantomData,
+};
+
+use crate::{Node, TREE_SIZE_SIZE};
+
+/// Position of a subtree within the underlying reader of a [`SeekableTree`]. Obtained via
+/// [`SeekableTree::root`] or by iterating [`SeekBranches`]. The range `start..end` includes the
+/// trailing size header of the subtree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SeekNode {
+    /// Offset of the first byte belonging to this subtree.
+    pub start: u64,
+    /// Offset one past the last byte (the last byte of the size header) of this subtree.
+    pub end: u64,
+}
+
+/// A tree which is read lazily from a seekable source, e.g. a file. In contrast to [`crate::TreeVec`]
+/// the tree is not loaded into memory. Only size headers and values are fetched once they are
+/// needed to navigate the tree.
+pub struct SeekableTree<N, R> {
+    _node_type: PhantomData<N>,
+    reader: R,
+    /// Upper bound for the number of bytes a serialized value may occupy.
+    max_value_size: usize,
+    /// Length of the reader in bytes, determined once during construction.
+    len: u64,
+}
+
+impl<N, R> SeekableTree<N, R>
+where
+    R: Read + Seek,
+{
+    /// Wraps a reader, which is expected to contain exactly one tree written by
+    /// [`crate::TreeBuilder`].
+    ///
+    /// # Parameters
+    ///
+    /// * `reader`: Source of the binary tree representation. Its length is determined by seeking
+    ///   to its end.
+    /// * `max_value_size`: Since values are read from the back and their size is only known after
+    ///   decoding them, we need to know how many bytes to fetch in front of each size header. This
+    ///   must be at least as large as the largest serialized value in the tree. E.g. `4` for
+    ///   [`crate::LeI32`]. Less bytes are read if the subtree is smaller. Reading a larger value
+    ///   fails with [`io::ErrorKind::InvalidData`].
+    pub fn new(mut reader: R, max_value_size: usize) -> io::Result<Self> {
+        let len = reader.seek(SeekFrom::End(0))?;
+        Ok(Self {
+            _node_type: PhantomData,
+            reader,
+            max_value_size,
+            len,
+        })
+    }
+
+    /// Location of the root node, spanning the entire reader.
+    pub fn root(&self) -> SeekNode {
+        SeekNode {
+            start: 0,
+            end: self.len,
+        }
+    }
+
+    /// Like [`Self::read_node`] for [`Self::root`], but first checks that the size header of the
+    /// root spans the entire reader. This detects e.g. truncated files before any child is
+    /// visited. Fails with [`io::ErrorKind::InvalidData`] otherwise. Can be called any number of
+    /// times to traverse the tree again.
+    pub fn read_root(&mut self) -> io::Result<(N::Value, SeekBranches<'_, N, R>)>
+    where
+        N: Node,
+    {
+        let header_start = self
+            .len
+            .checked_sub(TREE_SIZE_SIZE as u64)
+            .ok_or_else(|| malformed("Tree is too small to hold a size header."))?;
+        let mut size_bytes = [0; TREE_SIZE_SIZE];
+        self.reader.seek(SeekFrom::Start(header_start))?;
+        self.reader.read_exact(&mut size_bytes)?;
+        if N::SIZE_ENDIANNESS.decode(size_bytes) != header_start {
+            return Err(malformed(
+                "Size of root does not match the length of the reader.",
+            ));
+        }
+        self.read_node(self.root())
+    }
+
+    /// Deserializes the value of the node at `node` and returns an iterator over the locations of
+    /// its children. Only the value is read, the children are located lazily by the iterator.
+    /// Fails with [`io::ErrorKind::InvalidData`] if the subtree is too small for a valid value,
+    /// decoded with [`Node::try_read_value`].
+    pub fn read_node(&mut self, node: SeekNode) -> io::Result<(N::Value, SeekBranches<'_, N, R>)>
+    where
+        N: Node,
+    {
+        let header_start = node
+            .end
+            .checked_sub(TREE_SIZE_SIZE as u64)
+            .filter(|&header_start| header_start >= node.start)
+            .ok_or_else(|| malformed("Subtree is too small to hold a size header."))?;
+        let window = (self.max_value_size as u64).min(header_start - node.start);
+        let mut value_bytes = vec![0; window as usize];
+        self.reader.seek(SeekFrom::Start(header_start - window))?;
+        self.reader.read_exact(&mut value_bytes)?;
+        // Fails if the value requires more bytes than the subtree holds, or than `max_value_size`
+        // allows.
+        let (size_value, value) = N::try_read_value(&value_bytes)
+            .ok_or_else(|| malformed("Subtree does not hold a valid value within the window."))?;
+        let branches = SeekBranches {
+            _node_type: PhantomData,
+            reader: &mut self.reader,
+            start: node.start,
+            end: header_start - size_value as u64,
+        };
+        Ok((value, branches))
+    }
+
+    /// Returns the inner reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Iterates lazily over the locations of the children of a node in a [`SeekableTree`]. Like
+/// [`crate::Branches`] children are yielded starting with the last one written. Each step reads
+/// exactly one size header.
+pub struct SeekBranches<'a, N, R> {
+    _node_type: PhantomData<N>,
+    reader: &'a mut R,
+    start: u64,
+    end: u64,
+}
+
+impl<'a, N, R> Iterator for SeekBranches<'a, N, R>
+where
+    N: Node,
+    R: Read + Seek,
+{
+    type Item = io::Result<SeekNode>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.start == self.end {
+            return None;
+        }
+        let result = self.next_branch();
+        if result.is_err() {
+            // Do not keep on reading garbage after the first error
+            self.end = self.start;
+        }
+        Some(result)
+    }
+}
+
+impl<'a, N, R> SeekBranches<'a, N, R>
+where
+    N: Node,
+    R: Read + Seek,
+{
+    fn next_branch(&mut self) -> io::Result<SeekNode> {
+        let header_start = self
+            .end
+            .checked_sub(TREE_SIZE_SIZE as u64)
+            .filter(|&header_start| header_start >= self.start)
+            .ok_or_else(|| malformed("Branch is too small to hold a size header."))?;
+        let mut size_bytes = [0; TREE_SIZE_SIZE];
+        self.reader.seek(SeekFrom::Start(header_start))?;
+        self.reader.read_exact(&mut size_bytes)?;
+        let tree_size = N::SIZE_ENDIANNESS.decode(size_bytes);
+        let subtree_start = header_start
+            .checked_sub(tree_size)
+            .filter(|&subtree_start| subtree_start >= self.start)
+            .ok_or_else(|| malformed("Size of branch exceeds the bytes of its parent."))?;
+        let node = SeekNode {
+            start: subtree_start,
+            end: self.end,
+        };
+        // Advance iterator by excluding the subtree just returned
+        self.end = subtree_start;
+        Ok(node)
+    }
+}
+
+fn malformed(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
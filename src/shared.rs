@@ -0,0 +1,46 @@
+use std::{marker::PhantomData, ops::Deref, sync::Arc};
+
+use crate::{TreeSlice, TreeVec};
+
+/// A tree in contigious memory with shared ownership. Cloning it is cheap, since only the
+/// reference count of the underlying buffer is incremented. Obtained via [`TreeVec::into_shared`].
+pub struct TreeArc<N> {
+    _node_type: PhantomData<N>,
+    bytes: Arc<[u8]>,
+}
+
+impl<N> TreeArc<N> {
+    pub fn as_tree_slice(&self) -> &TreeSlice<N> {
+        TreeSlice::from_slice(&self.bytes)
+    }
+}
+
+// Implemented manually, since deriving would require `N: Clone`.
+impl<N> Clone for TreeArc<N> {
+    fn clone(&self) -> Self {
+        TreeArc {
+            _node_type: PhantomData,
+            bytes: self.bytes.clone(),
+        }
+    }
+}
+
+impl<N> Deref for TreeArc<N> {
+    type Target = TreeSlice<N>;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_tree_slice()
+    }
+}
+
+impl<N> TreeVec<N> {
+    /// Moves the tree into a buffer with shared ownership, so it can be cloned cheaply and e.g. be
+    /// shared between threads. A footer is not part of the tree and therefore dropped.
+    pub fn into_shared(mut self) -> TreeArc<N> {
+        self.bytes.truncate(self.bytes.len() - self.footer_len);
+        TreeArc {
+            _node_type: PhantomData,
+            bytes: self.bytes.into(),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::ops::RangeInclusive;
+
+use crate::{Node, TreeSlice};
+
+/// Describes the allowed shape of a tree level by level. Used by [`TreeSlice::validate_against`].
+/// Levels are described starting with the root. Nodes deeper than the last described level are
+/// not constrained.
+pub struct StructuralSpec<V> {
+    levels: Vec<LevelSpec<V>>,
+}
+
+/// Constraint on the values of a level.
+type ValuePredicate<V> = Box<dyn Fn(&V) -> bool>;
+
+/// Constraints for all nodes on a single level of the tree.
+struct LevelSpec<V> {
+    children: RangeInclusive<usize>,
+    value: Option<ValuePredicate<V>>,
+}
+
+impl<V> StructuralSpec<V> {
+    /// A spec without any levels, which every tree satisfies.
+    pub fn new() -> Self {
+        Self { levels: Vec::new() }
+    }
+
+    /// Describes the next level. Each node on it must have a number of children within
+    /// `children`.
+    pub fn level(mut self, children: RangeInclusive<usize>) -> Self {
+        self.levels.push(LevelSpec {
+            children,
+            value: None,
+        });
+        self
+    }
+
+    /// Like [`Self::level`], but additionally each value on the level must satisfy `predicate`.
+    pub fn level_with_values(
+        mut self,
+        children: RangeInclusive<usize>,
+        predicate: impl Fn(&V) -> bool + 'static,
+    ) -> Self {
+        self.levels.push(LevelSpec {
+            children,
+            value: Some(Box::new(predicate)),
+        });
+        self
+    }
+}
+
+impl<V> Default for StructuralSpec<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A node violating a [`StructuralSpec`]. Nodes are identified by their index in pre-order, with
+/// children in the order they have been written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// The number of children is not within the range allowed for the level.
+    ChildCount {
+        preorder_index: usize,
+        depth: usize,
+        count: usize,
+    },
+    /// The value does not satisfy the predicate of the level.
+    Value { preorder_index: usize, depth: usize },
+}
+
+impl<N> TreeSlice<N> {
+    /// Checks every node against the constraints `spec` describes for its level. The root is at
+    /// depth `0`. Reports all violations, ordered by the pre-order index of the offending node,
+    /// rather than stopping at the first one.
+    pub fn validate_against(
+        &self,
+        spec: &StructuralSpec<N::Value>,
+    ) -> Result<(), Vec<ValidationError>>
+    where
+        N: Node,
+    {
+        let mut errors = Vec::new();
+        for visit in self.walk() {
+            let (preorder_index, depth) = (visit.index, visit.depth);
+            if let Some(level) = spec.levels.get(depth) {
+                if !level.children.contains(&visit.num_children) {
+                    errors.push(ValidationError::ChildCount {
+                        preorder_index,
+                        depth,
+                        count: visit.num_children,
+                    });
+                }
+                if level
+                    .value
+                    .as_ref()
+                    .is_some_and(|predicate| !predicate(&visit.value))
+                {
+                    errors.push(ValidationError::Value {
+                        preorder_index,
+                        depth,
+                    });
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
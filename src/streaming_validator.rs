@@ -0,0 +1,107 @@
+use std::marker::PhantomData;
+
+use crate::{read_size, Node, TreeError, TreeSize, TreeSlice, TreeVec, TREE_SIZE_SIZE};
+
+/// Validates a tree whose bytes arrive in chunks, e.g. over the network.
+///
+/// # Limits
+///
+/// The size of each node is stored after its children and its value. So the bytes arriving first
+/// can only be interpreted once the size headers following them are known, and the header of the
+/// root arrives last. In general the tree can therefore only be validated once all of its bytes
+/// are received. The validator buffers the chunks and checks what it can early:
+///
+/// * If the total length is known in advance (see [`Self::with_expected_len`]), receiving more
+///   bytes than expected is reported right away, and the size header of the root is checked as
+///   soon as the last chunk arrives.
+/// * Everything else is checked by [`Self::finish`].
+pub struct StreamingValidator<N> {
+    _node_type: PhantomData<N>,
+    buffer: Vec<u8>,
+    expected_len: Option<usize>,
+}
+
+/// Returned by [`StreamingValidator::feed`] for each chunk which did not reveal an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    /// More bytes are required (or may follow) before the tree can be validated.
+    Incomplete { received: usize },
+    /// All expected bytes have been received and the size header of the root matches their
+    /// number. Call [`StreamingValidator::finish`] to validate the rest of the tree.
+    Complete,
+}
+
+impl<N> StreamingValidator<N> {
+    /// Validator for a tree of unknown length.
+    pub fn new() -> Self {
+        Self {
+            _node_type: PhantomData,
+            buffer: Vec::new(),
+            expected_len: None,
+        }
+    }
+
+    /// Validator for a tree with a total length of `len` bytes. Allows for detecting excess bytes
+    /// and a mismatching root size before [`Self::finish`] is called.
+    pub fn with_expected_len(len: usize) -> Self {
+        Self {
+            _node_type: PhantomData,
+            buffer: Vec::with_capacity(len),
+            expected_len: Some(len),
+        }
+    }
+
+    /// Appends the next chunk of bytes.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Progress, TreeError>
+    where
+        N: Node,
+    {
+        self.buffer.extend_from_slice(chunk);
+        let Some(expected_len) = self.expected_len else {
+            return Ok(Progress::Incomplete {
+                received: self.buffer.len(),
+            });
+        };
+        if self.buffer.len() > expected_len {
+            return Err(TreeError::Malformed {
+                reason: "Received more bytes than expected.",
+            });
+        }
+        if self.buffer.len() < expected_len {
+            return Ok(Progress::Incomplete {
+                received: self.buffer.len(),
+            });
+        }
+        let total_size = self.buffer.len();
+        if total_size < TREE_SIZE_SIZE
+            || read_size::<N>(&self.buffer) != (total_size - TREE_SIZE_SIZE) as TreeSize
+        {
+            return Err(TreeError::Malformed {
+                reason: "Size of root does not match the number of bytes.",
+            });
+        }
+        Ok(Progress::Complete)
+    }
+
+    /// Call this once all chunks have been fed. Validates the entire tree and returns it.
+    pub fn finish(self) -> Result<TreeVec<N>, TreeError>
+    where
+        N: Node,
+    {
+        if let Some(expected_len) = self.expected_len {
+            if self.buffer.len() != expected_len {
+                return Err(TreeError::Malformed {
+                    reason: "Received fewer bytes than expected.",
+                });
+            }
+        }
+        TreeSlice::<N>::from_slice(&self.buffer).validate()?;
+        Ok(TreeVec::new(self.buffer))
+    }
+}
+
+impl<N> Default for StreamingValidator<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
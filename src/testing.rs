@@ -0,0 +1,44 @@
+//! Helpers for testing custom [`Node`] implementations. Requires the `testing` feature.
+
+use std::fmt::Debug;
+
+use crate::Node;
+
+/// Asserts that each of `values` survives being written with [`Node::write_value`] and read back
+/// with [`Node::read_value`] and [`Node::try_read_value`]. Also asserts that they report the number
+/// of bytes actually written. Values are read from the back of a buffer with unrelated bytes in front, just like
+/// they are read in a tree.
+///
+/// # Panics
+///
+/// If any of the assertions fails, or writing to a `Vec` fails.
+pub fn assert_node_roundtrips<N: Node>(values: &[N::Value])
+where
+    N::Value: PartialEq + Debug,
+{
+    /// Stands in for the bytes of children or siblings in front of a value.
+    const PREFIX: [u8; 3] = [0xAB; 3];
+    for value in values {
+        let mut bytes = PREFIX.to_vec();
+        let written = N::write_value(&mut bytes, value).expect("Writing to Vec must not fail");
+        assert_eq!(
+            PREFIX.len() + written,
+            bytes.len(),
+            "write_value must return the number of bytes written for {value:?}"
+        );
+        let (read, read_back) = N::read_value(&bytes);
+        assert_eq!(
+            written, read,
+            "read_value must consume the bytes written for {value:?}"
+        );
+        assert_eq!(
+            value, &read_back,
+            "Value must survive being written and read"
+        );
+        assert_eq!(
+            Some((read, read_back)),
+            N::try_read_value(&bytes),
+            "try_read_value must agree with read_value for {value:?}"
+        );
+    }
+}
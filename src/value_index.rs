@@ -0,0 +1,59 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    io::{self, Write},
+    ops::Range,
+};
+
+use crate::{Node, TreeBuilder, TreeSize};
+
+/// Maps each value to the byte ranges of the subtrees, whose root holds that value. See
+/// [`IndexingTreeBuilder`].
+pub type ValueIndex<V> = HashMap<V, Vec<Range<usize>>>;
+
+/// Wraps a [`TreeBuilder`] and records for each value the byte ranges of the subtrees rooted at
+/// nodes holding it, while they are written. This allows looking up subtrees by value in the
+/// finished tree. Ranges instead of plain offsets are recorded, since a node and its first
+/// written child start at the same offset.
+pub struct IndexingTreeBuilder<N: Node, W> {
+    builder: TreeBuilder<N, W>,
+    index: ValueIndex<N::Value>,
+}
+
+impl<N, W> IndexingTreeBuilder<N, W>
+where
+    N: Node,
+    N::Value: Hash + Eq + Clone,
+{
+    pub fn new(writer: W) -> Self {
+        Self {
+            builder: TreeBuilder::new(writer),
+            index: HashMap::new(),
+        }
+    }
+
+    /// Adds a node to the tree, just like [`TreeBuilder::write_node`] does, and records its byte
+    /// range.
+    pub fn write_node(&mut self, value: &N::Value, num_children: usize) -> io::Result<()>
+    where
+        W: Write,
+    {
+        self.builder.write_node(value, num_children)?;
+        let end = self.builder.bytes_written();
+        let size: TreeSize = *self.builder.open_node_sizes.last().unwrap();
+        let range = (end - size) as usize..end as usize;
+        self.index.entry(value.clone()).or_default().push(range);
+        Ok(())
+    }
+
+    /// Finishes the tree like [`TreeBuilder::finish`]. Returns the inner writer together with the
+    /// index. Ranges for each value are ordered by the time their nodes have been written, i.e.
+    /// in post-order.
+    pub fn finish(self) -> io::Result<(W, ValueIndex<N::Value>)>
+    where
+        W: Write,
+    {
+        let writer = self.builder.finish()?;
+        Ok((writer, self.index))
+    }
+}
@@ -0,0 +1,69 @@
+//! Requires a nightly compiler. Run with `cargo +nightly test --features allocator_api`.
+#![cfg(feature = "allocator_api")]
+#![feature(allocator_api)]
+
+use std::{
+    alloc::{AllocError, Allocator, Layout},
+    cell::{Cell, UnsafeCell},
+    ptr::NonNull,
+};
+
+use contigious_tree::{TreeBuilder, TreeVec, U8};
+
+#[test]
+fn to_parent_array_in_bump_allocator() {
+    // Given
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 2).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+    let bump = Bump::new();
+
+    // When
+    let (values, parents) = tree.to_parent_array_in(&bump);
+
+    // Then
+    assert_eq!([3, 1, 2], values.as_slice());
+    assert_eq!([0, 0, 0], parents.as_slice());
+    assert!(bump.used() > 0);
+}
+
+/// Hands out memory from a fixed buffer and never frees it.
+struct Bump {
+    buffer: UnsafeCell<[u8; 1024]>,
+    used: Cell<usize>,
+}
+
+impl Bump {
+    fn new() -> Self {
+        Self {
+            buffer: UnsafeCell::new([0; 1024]),
+            used: Cell::new(0),
+        }
+    }
+
+    fn used(&self) -> usize {
+        self.used.get()
+    }
+}
+
+unsafe impl Allocator for Bump {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let base = self.buffer.get() as *mut u8;
+        let start =
+            (base as usize + self.used.get()).next_multiple_of(layout.align()) - base as usize;
+        let end = start + layout.size();
+        if end > 1024 {
+            return Err(AllocError);
+        }
+        self.used.set(end);
+        let ptr = unsafe { base.add(start) };
+        Ok(NonNull::slice_from_raw_parts(
+            NonNull::new(ptr).unwrap(),
+            layout.size(),
+        ))
+    }
+
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+}
@@ -0,0 +1,75 @@
+//! Run with `cargo test --features content-id`.
+#![cfg(feature = "content-id")]
+
+use contigious_tree::{Bytes, TreeBuilder, TreeVec, U8};
+
+#[test]
+fn equal_trees_share_content_id() {
+    // Given
+    let tree = two_children(1, 2);
+    let same = two_children(1, 2);
+    let other = two_children(2, 1);
+
+    // When
+    let id = tree.content_id();
+
+    // Then
+    assert_eq!(id, same.content_id());
+    assert_ne!(id, other.content_id());
+    // Digest as computed by Python's `hashlib.sha256`
+    assert_eq!(
+        "1f0c8385d487d43158ea3407a05bd7e940db4358bcbbb99ccf4213887100f98a",
+        id.iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>()
+    );
+}
+
+#[test]
+fn content_id_pads_trees_around_block_boundaries() {
+    // Given leaves which have `len + 16` bytes, so that the padding spills into a second block or
+    // the tree fills whole blocks.
+    let expected = [
+        (
+            39,
+            "4aee2a25b41a2624100b6624bada58a49db2b4bed988a3234a2c54b670567055",
+        ),
+        (
+            40,
+            "3bae3ec63cf79626c9f01fcf2484f55759af12eb4dd1a85d031dca633e593420",
+        ),
+        (
+            48,
+            "fe950c03100544afd9c4332b5c6009e07f29d2e443ede474f22f67fd4180683a",
+        ),
+        (
+            104,
+            "bed35a5cd6fd370eb0367198c7a224d0f055c866200b823de243164235c290bc",
+        ),
+    ];
+
+    for (len, digest) in expected {
+        // When
+        let mut builder = TreeBuilder::<Bytes, _>::new(Vec::new());
+        builder.write_node(&vec![0xAB; len], 0).unwrap();
+        let tree = TreeVec::<Bytes>::new(builder.finish().unwrap());
+
+        // Then digest as computed by Python's `hashlib.sha256`
+        assert_eq!(
+            digest,
+            tree.content_id()
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>()
+        );
+    }
+}
+
+/// Root with value `3` and two leaves as children.
+fn two_children(first: u8, second: u8) -> TreeVec<U8> {
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&first, 0).unwrap();
+    builder.write_node(&second, 0).unwrap();
+    builder.write_node(&3, 2).unwrap();
+    TreeVec::new(builder.finish().unwrap())
+}
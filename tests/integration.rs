@@ -1,4 +1,15 @@
-use contigious_tree::{TreeBuilder, TreeVec, U8, LeI32};
+use std::{
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+};
+
+use contigious_tree::{
+    skip_subtree, swap_value_endianness, BeI32, BeI64, BeU32, BeU64, BigEndianSizes, Bool, Bytes,
+    DepthFirstIter, IndexingTreeBuilder, IntoTree, LeI32, Node, OrderedBranches, PackedBoolBuilder,
+    PackedBools, PackedChild, Progress, SeekableTree, StreamingValidator, StructuralSpec, TreeArc,
+    TreeBuilder, TreeError, TreeMetrics, TreeSlice, TreeVec, Utf8, ValidationError, VarI64, VarU64,
+    U8,
+};
 
 #[test]
 fn leaf() {
@@ -78,3 +89,2401 @@ fn three_successive_nodes() {
     assert_eq!(1, value);
     assert!(branches.next().is_none())
 }
+
+#[test]
+fn navigate_seekable_tree_reading_minimal_bytes() {
+    // Given a file with a root node, which owns a wide subtree and a chain of four nodes.
+    let path = std::env::temp_dir().join("contigious_tree_navigate_seekable_tree.bin");
+    let file = File::create(&path).unwrap();
+    let mut builder = TreeBuilder::<U8, _>::new(file);
+    for _ in 0..100 {
+        builder.write_node(&0, 0).unwrap();
+    }
+    builder.write_node(&0, 100).unwrap();
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 1).unwrap();
+    builder.write_node(&3, 1).unwrap();
+    builder.write_node(&4, 1).unwrap();
+    builder.write_node(&5, 2).unwrap();
+    builder.finish().unwrap();
+
+    // When navigating along the last written children to the deepest node
+    let reader = CountingReader::new(File::open(&path).unwrap());
+    let mut tree = SeekableTree::<U8, _>::new(reader, 1).unwrap();
+    let mut node = tree.root();
+    let mut values = Vec::new();
+    loop {
+        let (value, mut branches) = tree.read_node(node).unwrap();
+        values.push(value);
+        match branches.next() {
+            Some(child) => node = child.unwrap(),
+            None => break,
+        }
+    }
+    let bytes_read = tree.into_inner().bytes_read;
+    fs::remove_file(&path).unwrap();
+
+    // Then
+    assert_eq!(vec![5, 4, 3, 2, 1], values);
+    // One byte for each of the five values and eight bytes for each of the four size headers.
+    assert_eq!(5 + 4 * 8, bytes_read);
+}
+
+#[test]
+fn shape_similarity() {
+    // Given
+    let chain = chain_fixture();
+    let other_chain = {
+        let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+        builder.write_node(&7, 0).unwrap();
+        builder.write_node(&8, 1).unwrap();
+        builder.write_node(&9, 1).unwrap();
+        TreeVec::<U8>::new(builder.finish().unwrap())
+    };
+    let wide = {
+        let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+        for _ in 0..10 {
+            builder.write_node(&0, 0).unwrap();
+        }
+        builder.write_node(&0, 10).unwrap();
+        TreeVec::<U8>::new(builder.finish().unwrap())
+    };
+
+    // When
+    let same_shape = chain.shape_similarity(&other_chain);
+    let different_shape = chain.shape_similarity(&wide);
+
+    // Then
+    assert_eq!(1.0, same_shape);
+    assert!(different_shape < 0.5);
+}
+
+#[test]
+fn into_subtrees_dfs() {
+    // Given
+    let tree = chain_fixture();
+
+    // When
+    let subtrees: Vec<_> = tree.into_subtrees_dfs().collect();
+
+    // Then
+    let values: Vec<_> = subtrees
+        .iter()
+        .map(|subtree| subtree.read_node().0)
+        .collect();
+    assert_eq!(vec![3, 2, 1], values);
+    let (_, mut branches) = subtrees[1].read_node();
+    assert_eq!(1, branches.next().unwrap().read_node().0);
+    assert!(branches.next().is_none());
+}
+
+#[test]
+fn packed_bool_leaves_are_smaller() {
+    // Given
+    let leaves: Vec<bool> = (0..16).map(|index| index % 3 == 0).collect();
+
+    // When
+    let mut builder = TreeBuilder::<Bool, _>::new(Vec::new());
+    for leaf in &leaves {
+        builder.write_node(leaf, 0).unwrap();
+    }
+    builder.write_node(&true, 16).unwrap();
+    let unpacked = builder.finish().unwrap();
+    let mut builder = PackedBoolBuilder::new(Vec::new());
+    builder.write_leaves(&leaves).unwrap();
+    builder.write_node(true, 16).unwrap();
+    let packed = builder.finish().unwrap();
+
+    // Then
+    // Each of the 17 nodes takes one byte for the value and eight bytes for the size
+    assert_eq!(17 * 9, unpacked.len());
+    // Two groups of eight leaves and the root, each taking two bytes for the values and eight for
+    // size.
+    assert_eq!(3 * 10, packed.len());
+    let tree = TreeVec::<PackedBools>::new(packed);
+    let (value, children) = tree.read_packed_node();
+    assert!(value);
+    let read_leaves: Vec<bool> = children
+        .iter()
+        .rev()
+        .map(|child| match child {
+            PackedChild::Leaf(leaf) => *leaf,
+            PackedChild::Node(_) => panic!("Expected leaf"),
+        })
+        .collect();
+    assert_eq!(leaves, read_leaves);
+}
+
+#[test]
+fn packed_bool_node_must_not_split_group() {
+    // Given
+    let mut builder = PackedBoolBuilder::new(Vec::new());
+    builder.write_leaves(&[true, false]).unwrap();
+
+    // When
+    let result = builder.write_node(true, 1);
+
+    // Then
+    assert_eq!(io::ErrorKind::InvalidInput, result.unwrap_err().kind());
+}
+
+#[test]
+fn packed_bool_finish_counts_packed_leaves_as_roots() {
+    // Given three leaves packed into a single node, without a parent
+    let mut builder = PackedBoolBuilder::new(Vec::new());
+    builder.write_leaves(&[true, false, true]).unwrap();
+
+    // When
+    let result = builder.finish();
+
+    // Then
+    assert_eq!(io::ErrorKind::InvalidData, result.unwrap_err().kind());
+}
+
+#[test]
+fn replace_where() {
+    // Given
+    let tree = two_children_fixture();
+
+    // When
+    let replaced = tree
+        .replace_where(|&value| value > 1, |value| value * 10)
+        .unwrap();
+
+    // Then
+    let (value, mut branches) = replaced.read_node();
+    assert_eq!(30, value);
+    assert_eq!(20, branches.next().unwrap().read_node().0);
+    assert_eq!(1, branches.next().unwrap().read_node().0);
+    assert!(branches.next().is_none());
+}
+
+#[test]
+fn is_isomorphic_across_node_types() {
+    // Given
+    let mut builder = TreeBuilder::<LeI32, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 2).unwrap();
+    let wide = TreeVec::<LeI32>::new(builder.finish().unwrap());
+    let narrow = two_children_fixture();
+    let chain = chain_fixture();
+
+    // When
+    let relation = |&wide: &i32, &narrow: &u8| wide == narrow as i32;
+    let same = wide.is_isomorphic(&narrow, relation);
+    let different_shape = wide.is_isomorphic(&chain, relation);
+    let different_values = wide.is_isomorphic(&narrow, |_, _| false);
+
+    // Then
+    assert!(same);
+    assert!(!different_shape);
+    assert!(!different_values);
+}
+
+#[test]
+fn subtree_end_offset() {
+    // Given
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 2).unwrap();
+    let bytes = builder.finish().unwrap();
+    let tree = TreeSlice::<U8>::from_slice(&bytes);
+
+    // When
+    // Second child starts after the first child, which occupies one byte for its value and eight
+    // for its size.
+    let end = tree.subtree_end_offset(9).unwrap();
+
+    // Then
+    assert_eq!(18, end);
+    let subtree = TreeSlice::<U8>::from_slice(&bytes[9..end]);
+    let (value, mut branches) = subtree.read_node();
+    assert_eq!(2, value);
+    assert!(branches.next().is_none());
+    assert_eq!(bytes.len(), tree.subtree_end_offset(0).unwrap());
+    assert_eq!(
+        TreeError::NoSubtreeAt { offset: 3 },
+        tree.subtree_end_offset(3).unwrap_err()
+    );
+}
+
+#[test]
+fn columnar_round_trip() {
+    // Given
+    let mut builder = TreeBuilder::<LeI32, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 1).unwrap();
+    builder.write_node(&3, 0).unwrap();
+    builder.write_node(&4, 2).unwrap();
+    let bytes = builder.finish().unwrap();
+    let tree = TreeSlice::<LeI32>::from_slice(&bytes);
+
+    // When
+    let (values, structure) = tree.to_columnar();
+    let reassembled = TreeVec::<LeI32>::from_columnar(&values, &structure).unwrap();
+
+    // Then
+    assert_eq!(4 * 4, values.len());
+    assert_eq!(4 * 16, structure.len());
+    assert!(tree.is_isomorphic(&reassembled, |a, b| a == b));
+    assert_eq!((values, structure), reassembled.to_columnar());
+}
+
+#[test]
+fn value_at() {
+    // Given
+    let tree = chain_fixture();
+
+    // When
+    let root = tree.value_at(0);
+    let grand_child = tree.value_at(2);
+    let out_of_range = tree.value_at(3);
+
+    // Then
+    assert_eq!(Some(3), root);
+    assert_eq!(Some(1), grand_child);
+    assert_eq!(None, out_of_range);
+}
+
+#[test]
+fn subtree_size_histogram() {
+    // Given
+    // Leaves occupy 9 bytes, the root 27
+    let tree = two_children_fixture();
+
+    // When
+    let histogram = tree.subtree_size_histogram(10);
+
+    // Then
+    assert_eq!(vec![2, 0, 1], histogram);
+}
+
+#[test]
+fn write_canonical() {
+    // Given two trees which only differ in the order of children
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&5, 0).unwrap();
+    builder.write_node(&1, 1).unwrap();
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&3, 0).unwrap();
+    builder.write_node(&9, 3).unwrap();
+    let unsorted = TreeVec::<U8>::new(builder.finish().unwrap());
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&5, 0).unwrap();
+    builder.write_node(&1, 1).unwrap();
+    builder.write_node(&3, 0).unwrap();
+    builder.write_node(&9, 3).unwrap();
+    let sorted = builder.finish().unwrap();
+
+    // When
+    let mut out = Vec::new();
+    unsorted.write_canonical(&mut out).unwrap();
+    let mut out_sorted = Vec::new();
+    TreeSlice::<U8>::from_slice(&sorted)
+        .write_canonical(&mut out_sorted)
+        .unwrap();
+
+    // Then
+    assert_eq!(sorted, out);
+    assert_eq!(sorted, out_sorted);
+}
+
+#[test]
+fn checked_branches_terminate_on_empty_subtrees() {
+    // Given a root with value 42, whose branches are two size headers claiming zero bytes.
+    let mut bytes = vec![0; 2 * 8];
+    bytes.push(42);
+    bytes.extend_from_slice(&17u64.to_le_bytes());
+    let tree = TreeSlice::<U8>::from_slice(&bytes);
+
+    // When
+    let (_, mut branches) = tree.read_node();
+    let first = branches.checked_next();
+    let second = branches.checked_next();
+    let third = branches.checked_next();
+
+    // Then an empty subtree can not hold a value, and iteration stops after the error
+    assert!(matches!(first, Some(Err(TreeError::Malformed { .. }))));
+    assert!(second.is_none());
+    assert!(third.is_none());
+}
+
+#[test]
+fn checked_branches_reject_out_of_bounds_sizes() {
+    // Given a root with value 42, whose only branch claims to be larger than the entire tree.
+    let mut bytes = 100u64.to_le_bytes().to_vec();
+    bytes.push(42);
+    bytes.extend_from_slice(&9u64.to_le_bytes());
+    let tree = TreeSlice::<U8>::from_slice(&bytes);
+
+    // When
+    let (_, mut branches) = tree.read_node();
+    let first = branches.checked_next();
+    let second = branches.checked_next();
+
+    // Then
+    assert_eq!(
+        Some(Err(TreeError::SizeOutOfBounds {
+            offset: 0,
+            claimed: 100,
+            remaining: 0
+        })),
+        first
+    );
+    assert!(second.is_none());
+}
+
+#[test]
+fn checked_branches_reject_truncated_size() {
+    // Given a root with value 42, with three bytes in front of it. Too little for a size header.
+    let bytes = [1, 2, 3, 42, 4, 0, 0, 0, 0, 0, 0, 0];
+    let tree = TreeSlice::<U8>::from_slice(&bytes);
+
+    // When
+    let (_, mut branches) = tree.read_node();
+
+    // Then
+    assert!(matches!(
+        branches.checked_next(),
+        Some(Err(TreeError::Malformed { .. }))
+    ));
+}
+
+#[test]
+fn distinct_value_count() {
+    // Given
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 3).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When
+    let count = tree.distinct_value_count();
+
+    // Then
+    assert_eq!(2, count);
+}
+
+#[test]
+fn value_frequencies() {
+    // Given
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&1, 3).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When
+    let frequencies = tree.value_frequencies();
+
+    // Then
+    assert_eq!(2, frequencies.len());
+    assert_eq!(3, frequencies[&1]);
+    assert_eq!(1, frequencies[&2]);
+}
+
+#[test]
+fn reverse_children() {
+    // Given
+    let tree = two_children_fixture();
+
+    // When
+    let reversed = tree.reverse_children().unwrap();
+
+    // Then
+    let (value, mut branches) = reversed.read_node();
+    assert_eq!(3, value);
+    // Branches yields the last written child first. Which is now the one with value 1.
+    assert_eq!(1, branches.next().unwrap().read_node().0);
+    assert_eq!(2, branches.next().unwrap().read_node().0);
+    assert!(branches.next().is_none());
+}
+
+#[test]
+fn find_path() {
+    // Given
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 0).unwrap();
+    builder.write_node(&4, 2).unwrap();
+    builder.write_node(&5, 2).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When
+    let deep = tree.find_path(|&value| value == 3);
+    let root = tree.find_path(|&value| value == 5);
+    let missing = tree.find_path(|&value| value == 6);
+
+    // Then
+    assert_eq!(Some(vec![1, 1]), deep);
+    assert_eq!(Some(vec![]), root);
+    assert_eq!(None, missing);
+}
+
+#[test]
+fn streaming_validator_in_small_chunks() {
+    // Given
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 1).unwrap();
+    builder.write_node(&3, 0).unwrap();
+    builder.write_node(&4, 2).unwrap();
+    let bytes = builder.finish().unwrap();
+
+    // When
+    let mut validator = StreamingValidator::<U8>::with_expected_len(bytes.len());
+    let mut progress = Vec::new();
+    for chunk in bytes.chunks(5) {
+        progress.push(validator.feed(chunk).unwrap());
+    }
+    let tree = validator.finish().unwrap();
+
+    // Then
+    assert_eq!(Progress::Incomplete { received: 5 }, progress[0]);
+    assert_eq!(Progress::Complete, *progress.last().unwrap());
+    assert_eq!(4, tree.read_node().0);
+}
+
+#[test]
+fn streaming_validator_detects_forest() {
+    // Given two trees in one buffer
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    let bytes = builder.finish_forest().unwrap();
+
+    // When
+    let mut validator = StreamingValidator::<U8>::new();
+    for chunk in bytes.chunks(3) {
+        validator.feed(chunk).unwrap();
+    }
+    let result = validator.finish();
+
+    // Then
+    assert!(matches!(result, Err(TreeError::Malformed { .. })));
+}
+
+#[test]
+fn total_value_bytes() {
+    // Given
+    let mut builder = TreeBuilder::<LeI32, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 2).unwrap();
+    let bytes = builder.finish().unwrap();
+    let tree = TreeSlice::<LeI32>::from_slice(&bytes);
+
+    // When
+    let value_bytes = tree.total_value_bytes();
+
+    // Then
+    assert_eq!(3 * 4, value_bytes);
+    // Remainder are the size headers of the three nodes
+    assert_eq!(bytes.len(), value_bytes + 3 * 8);
+}
+
+#[test]
+fn from_sorted() {
+    // Given
+    let sorted: Vec<u8> = (1..=15).collect();
+
+    // When
+    let tree = TreeVec::<U8>::from_sorted(&sorted).unwrap();
+
+    // Then
+    assert_eq!(4, tree.depth());
+    assert_eq!(sorted, tree.values_inorder().collect::<Vec<_>>());
+}
+
+#[test]
+fn values_inorder() {
+    // Given
+    let tree = two_children_fixture();
+
+    // When
+    let values: Vec<_> = tree.values_inorder().collect();
+
+    // Then
+    assert_eq!(vec![1, 3, 2], values);
+}
+
+#[test]
+fn subtree_table() {
+    // Given
+    let tree = two_children_fixture();
+
+    // When
+    let table = tree.subtree_table();
+
+    // Then
+    assert_eq!(3, table.len());
+    assert!(std::ptr::eq(tree.as_tree_slice(), table[0]));
+    let (_, mut branches) = tree.read_node();
+    let second = branches.next().unwrap();
+    let first = branches.next().unwrap();
+    assert!(std::ptr::eq(first, table[1]));
+    assert!(std::ptr::eq(second, table[2]));
+    for (index, subtree) in table.iter().enumerate() {
+        assert_eq!(tree.value_at(index), Some(subtree.read_node().0));
+    }
+}
+
+#[test]
+fn write_node_with_children() {
+    // Given
+    let chain = chain_fixture();
+    let two_children = two_children_fixture();
+
+    // When
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder
+        .write_node_with_children(&42, &[&chain, &two_children])
+        .unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // Then
+    let (value, mut branches) = tree.read_node();
+    assert_eq!(42, value);
+    let second = branches.next().unwrap();
+    let first = branches.next().unwrap();
+    assert!(branches.next().is_none());
+    assert!(first.is_isomorphic(&chain, |a, b| a == b));
+    assert!(second.is_isomorphic(&two_children, |a, b| a == b));
+}
+
+#[test]
+fn first_branch_depth() {
+    // Given
+    let chain = chain_fixture();
+    let two_children = two_children_fixture();
+
+    // When
+    let chain_depth = chain.first_branch_depth();
+    let two_children_depth = two_children.first_branch_depth();
+
+    // Then
+    assert_eq!(None, chain_depth);
+    assert_eq!(Some(0), two_children_depth);
+}
+
+#[test]
+fn big_endian_size_headers() {
+    // Given
+    let mut builder = TreeBuilder::<BigEndianSizes<U8>, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 1).unwrap();
+    let bytes = builder.finish().unwrap();
+
+    // When
+    let tree = TreeSlice::<BigEndianSizes<U8>>::from_slice(&bytes);
+    let (value, mut branches) = tree.read_node();
+    let child = branches.next().unwrap();
+    // Reading the same bytes with little endian size headers
+    let mismatched = TreeSlice::<U8>::from_slice(&bytes);
+    let (_, mut mismatched_branches) = mismatched.read_node();
+
+    // Then
+    // Size header of leaf is its last byte
+    assert_eq!([0, 0, 0, 0, 0, 0, 0, 1], bytes[1..9]);
+    assert_eq!(2, value);
+    assert_eq!(1, child.read_node().0);
+    assert!(branches.next().is_none());
+    assert!(matches!(
+        mismatched_branches.checked_next(),
+        Some(Err(TreeError::SizeOutOfBounds { .. }))
+    ));
+}
+
+#[test]
+fn metrics() {
+    // Given
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 0).unwrap();
+    builder.write_node(&4, 3).unwrap();
+    builder.write_node(&5, 0).unwrap();
+    builder.write_node(&6, 2).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When
+    let metrics = tree.metrics();
+
+    // Then
+    assert_eq!(
+        TreeMetrics {
+            node_count: 6,
+            leaf_count: 4,
+            height: 3,
+            max_width: 3,
+            average_branching_factor: 2.5,
+        },
+        metrics
+    );
+}
+
+#[test]
+fn from_parent_array() {
+    // Given
+    //   (0) root
+    //   ├── (1)
+    //   │    └── (3)
+    //   └── (2)
+    let values = [10, 11, 12, 13];
+    let parents = [0, 0, 0, 1];
+
+    // When
+    let tree = TreeVec::<U8>::from_parent_array(&values, &parents).unwrap();
+
+    // Then
+    let (value, mut branches) = tree.read_node();
+    assert_eq!(10, value);
+    let second = branches.next().unwrap();
+    let first = branches.next().unwrap();
+    assert!(branches.next().is_none());
+    assert_eq!(12, second.read_node().0);
+    let (value, mut branches) = first.read_node();
+    assert_eq!(11, value);
+    assert_eq!(13, branches.next().unwrap().read_node().0);
+}
+
+#[test]
+fn from_parent_array_rejects_cycles_and_multiple_roots() {
+    // Given
+    let values = [10, 11, 12];
+    // Nodes 1 and 2 are each others parents
+    let cycle = [0, 2, 1];
+    let two_roots = [0, 1, 0];
+
+    // When
+    let cycle_result = TreeVec::<U8>::from_parent_array(&values, &cycle);
+    let two_roots_result = TreeVec::<U8>::from_parent_array(&values, &two_roots);
+
+    // Then
+    assert!(matches!(
+        cycle_result,
+        Err(TreeError::InvalidParents { .. })
+    ));
+    assert!(matches!(
+        two_roots_result,
+        Err(TreeError::InvalidParents { .. })
+    ));
+}
+
+#[test]
+fn parent_array_round_trip() {
+    // Given
+    let values = vec![10, 11, 12, 13, 14];
+    let parents = vec![0, 0, 1, 1, 0];
+
+    // When
+    let tree = TreeVec::<U8>::from_parent_array(&values, &parents).unwrap();
+    let (round_trip_values, round_trip_parents) = tree.to_parent_array();
+
+    // Then
+    assert_eq!(values, round_trip_values);
+    assert_eq!(parents, round_trip_parents);
+}
+
+#[test]
+fn nodes_at_depth() {
+    // Given
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 0).unwrap();
+    builder.write_node(&4, 3).unwrap();
+    builder.write_node(&5, 0).unwrap();
+    builder.write_node(&6, 2).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When
+    let counts: Vec<_> = (0..4).map(|depth| tree.nodes_at_depth(depth)).collect();
+
+    // Then
+    assert_eq!(vec![1, 2, 3, 0], counts);
+}
+
+#[test]
+fn is_height_balanced() {
+    // Given
+    let sorted: Vec<u8> = (1..=10).collect();
+    let balanced = TreeVec::<U8>::from_sorted(&sorted).unwrap();
+    let chain = chain_fixture();
+
+    // When
+    let balanced_result = balanced.is_height_balanced(1);
+    let chain_result = chain.is_height_balanced(1);
+
+    // Then
+    assert!(balanced_result);
+    assert!(!chain_result);
+    assert!(chain.is_height_balanced(2));
+}
+
+#[test]
+fn finish_with_index() {
+    // Given
+    let write_nodes = |builder: &mut TreeBuilder<U8, Vec<u8>>| {
+        builder.write_node(&1, 0).unwrap();
+        builder.write_node(&2, 1).unwrap();
+        builder.write_node(&3, 0).unwrap();
+        builder.write_node(&4, 2).unwrap();
+    };
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    write_nodes(&mut builder);
+    let bytes = builder.finish().unwrap();
+
+    // When
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new()).with_index();
+    write_nodes(&mut builder);
+    let (tree, index) = builder.finish_with_index().unwrap();
+
+    // Then
+    // Pre-order: 4, 2, 1, 3. Each node takes 9 bytes.
+    assert_eq!(vec![0..36, 0..18, 0..9, 18..27], index);
+    assert_eq!(Some(4), tree.value_at(0));
+    for (range, expected) in index.into_iter().zip([4, 2, 1, 3]) {
+        let subtree = TreeSlice::<U8>::from_slice(&bytes[range]);
+        assert_eq!(expected, subtree.read_node().0);
+    }
+}
+
+#[test]
+fn longest_increasing_path() {
+    // Given
+    //  (1) root
+    //   ├── (3)
+    //   │    └── (2)
+    //   └── (2)
+    //        ├── (2)
+    //        └── (5)
+    //             └── (7)
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 1).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&7, 0).unwrap();
+    builder.write_node(&5, 1).unwrap();
+    builder.write_node(&2, 2).unwrap();
+    builder.write_node(&1, 2).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When
+    let path = tree.longest_increasing_path();
+
+    // Then
+    assert_eq!(vec![1, 2, 5, 7], path);
+}
+
+#[test]
+fn longest_increasing_path_ends_at_smaller_child() {
+    // Given a chain whose values increase only below the child of the root
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&4, 0).unwrap();
+    builder.write_node(&3, 1).unwrap();
+    builder.write_node(&2, 1).unwrap();
+    builder.write_node(&1, 1).unwrap();
+    builder.write_node(&5, 1).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When
+    let path = tree.longest_increasing_path();
+
+    // Then
+    assert_eq!(vec![5], path);
+}
+
+#[test]
+fn limit_children() {
+    // Given a root with four children, the first of which has three children of its own
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    for value in 1..=3 {
+        builder.write_node(&value, 0).unwrap();
+    }
+    builder.write_node(&4, 3).unwrap();
+    for value in 5..=7 {
+        builder.write_node(&value, 0).unwrap();
+    }
+    builder.write_node(&8, 4).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When
+    let limited = tree.limit_children(2).unwrap();
+
+    // Then
+    // Pre-order: root 8, its first child 4 with children 1 and 2, second child 5.
+    let (values, parents) = limited.to_parent_array();
+    assert_eq!(vec![8, 4, 1, 2, 5], values);
+    assert_eq!(vec![0, 0, 1, 1, 0], parents);
+}
+
+#[test]
+fn eq_filtered() {
+    // Given two trees which are equal, if nodes with value 0 are removed
+    // (1) -> (0) -> [(2), (3)]
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 0).unwrap();
+    builder.write_node(&0, 2).unwrap();
+    builder.write_node(&1, 1).unwrap();
+    let with_skipped = TreeVec::<U8>::new(builder.finish().unwrap());
+    // (1) -> [(2), (3)]
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 0).unwrap();
+    builder.write_node(&1, 2).unwrap();
+    let without_skipped = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When
+    let filtered = with_skipped.eq_filtered(&without_skipped, |&value| value != 0);
+    let unfiltered = with_skipped.eq_filtered(&without_skipped, |_| true);
+
+    // Then
+    assert!(filtered);
+    assert!(!unfiltered);
+}
+
+#[test]
+fn segment_tree() {
+    // Given
+    let leaves = [1, 2, 3, 4];
+
+    // When
+    let tree = TreeVec::<LeI32>::segment_tree(&leaves, |a, b| a + b).unwrap();
+
+    // Then
+    let (values, parents) = tree.to_parent_array();
+    assert_eq!(vec![10, 3, 1, 2, 7, 3, 4], values);
+    assert_eq!(vec![0, 0, 1, 1, 0, 4, 4], parents);
+}
+
+#[test]
+fn adler32() {
+    // Given
+    let tree = chain_fixture();
+    let same = chain_fixture();
+    let modified = tree.replace_where(|&value| value == 2, |_| 4).unwrap();
+
+    // When
+    let checksum = tree.adler32();
+
+    // Then
+    assert_eq!(same.adler32(), checksum);
+    assert_ne!(modified.adler32(), checksum);
+    // Reference value for the ASCII string "Wikipedia"
+    assert_eq!(
+        0x11E6_0398,
+        TreeSlice::<U8>::from_slice(b"Wikipedia").adler32()
+    );
+}
+
+#[test]
+fn group_by_root() {
+    // Given
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 1).unwrap();
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&1, 2).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When
+    let groups = tree.group_by_root();
+
+    // Then
+    assert_eq!(2, groups.len());
+    assert_eq!(3, groups[&1].len());
+    // Pre-order: Root first, then the leaf beneath 2, followed by the last written leaf.
+    assert!(std::ptr::eq(tree.as_tree_slice(), groups[&1][0]));
+    assert_eq!(1, groups[&2].len());
+    assert_eq!(1, groups[&2][0].read_node().1.next().unwrap().read_node().0);
+}
+
+#[test]
+fn value_bounds() {
+    // Given
+    let mut builder = TreeBuilder::<LeI32, _>::new(Vec::new());
+    builder.write_node(&-5, 0).unwrap();
+    builder.write_node(&12, 0).unwrap();
+    builder.write_node(&3, 2).unwrap();
+    let tree = TreeVec::<LeI32>::new(builder.finish().unwrap());
+    let empty = TreeVec::<LeI32>::from_sorted(&[]).unwrap();
+
+    // When
+    let bounds = tree.value_bounds();
+    let empty_bounds = empty.value_bounds();
+
+    // Then
+    assert_eq!(Some((-5, 12)), bounds);
+    assert_eq!(None, empty_bounds);
+}
+
+#[test]
+fn vs_pointer_layout_bytes() {
+    // Given
+    let tree = two_children_fixture();
+
+    // When
+    let (contigious, pointer) = tree.vs_pointer_layout_bytes();
+
+    // Then
+    // Each node holds one byte value and eight bytes size
+    assert_eq!(3 * 9, contigious);
+    // Each node holds one byte value and eight bytes count. The root two pointers to its children.
+    assert_eq!(3 * 9 + 2 * 8, pointer);
+}
+
+#[test]
+fn duplicate_subtrees() {
+    // Given a root with two identical children and a distinct one
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&7, 0).unwrap();
+    builder.write_node(&8, 0).unwrap();
+    builder.write_node(&7, 0).unwrap();
+    builder.write_node(&42, 3).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When
+    let duplicates = tree.duplicate_subtrees();
+
+    // Then
+    // Each leaf takes 9 bytes.
+    assert_eq!(vec![vec![0, 18]], duplicates);
+}
+
+#[test]
+fn replace_leaf_with_subtree() {
+    // Given
+    let tree = two_children_fixture();
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&5, 0).unwrap();
+    builder.write_node(&4, 1).unwrap();
+    let replacement = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When replacing the second child (pre-order index 2)
+    let replaced = tree.replace_subtree(2, &replacement).unwrap();
+
+    // Then
+    let (values, parents) = replaced.to_parent_array();
+    assert_eq!(vec![3, 1, 4, 5], values);
+    assert_eq!(vec![0, 0, 0, 2], parents);
+    assert!(tree.replace_subtree(3, &replacement).is_err());
+    let whole = tree.replace_subtree(0, &replacement).unwrap();
+    assert_eq!(replacement.to_parent_array(), whole.to_parent_array());
+}
+
+#[test]
+fn crc_footer() {
+    // Given
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 2).unwrap();
+    let mut bytes = builder.finish_with_crc_footer().unwrap();
+
+    // When
+    let tree = TreeVec::<U8>::with_crc_footer(bytes.clone()).unwrap();
+
+    // Then
+    assert_eq!(Ok(()), tree.verify_footer());
+    assert_eq!(vec![3, 1, 2], tree.to_parent_array().0);
+    bytes[0] ^= 0xFF;
+    let corrupted = TreeVec::<U8>::with_crc_footer(bytes).unwrap();
+    assert!(matches!(
+        corrupted.verify_footer(),
+        Err(TreeError::ChecksumMismatch { .. })
+    ));
+}
+
+#[test]
+fn crc_footer_matches_zlib() {
+    // Given
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&0x31, 0).unwrap();
+
+    // When
+    let bytes = builder.finish_with_crc_footer().unwrap();
+
+    // Then
+    // Checksum as computed by Python's `zlib.crc32`
+    assert_eq!(&0x13e9c0a1u32.to_le_bytes(), &bytes[9..]);
+}
+
+#[test]
+fn ancestor_descendant_pairs() {
+    // Given
+    let chain = chain_fixture();
+    let siblings = two_children_fixture();
+
+    // When
+    let chain_pairs: Vec<_> = chain.ancestor_descendant_pairs().collect();
+    let sibling_pairs: Vec<_> = siblings.ancestor_descendant_pairs().collect();
+
+    // Then
+    assert_eq!(vec![(3, 2), (3, 1), (2, 1)], chain_pairs);
+    assert_eq!(vec![(3, 1), (3, 2)], sibling_pairs);
+}
+
+#[test]
+fn share_tree_between_threads() {
+    // Given
+    let shared: TreeArc<U8> = two_children_fixture().into_shared();
+
+    // When
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let tree = shared.clone();
+            std::thread::spawn(move || tree.to_parent_array().0)
+        })
+        .collect();
+
+    // Then
+    for handle in handles {
+        assert_eq!(vec![3, 1, 2], handle.join().unwrap());
+    }
+}
+
+#[test]
+fn skip_one_of_two_children() {
+    // Given
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 2).unwrap();
+    let bytes = builder.finish().unwrap();
+    // Value of the root and its size header take 9 bytes
+    let children = &bytes[..(bytes.len() - 9)];
+
+    // When
+    let remainder = skip_subtree::<U8>(children).unwrap();
+
+    // Then
+    let (first, _) = TreeSlice::<U8>::from_slice(remainder).read_node();
+    assert_eq!(1, first);
+    assert_eq!(Ok(&[][..]), skip_subtree::<U8>(remainder));
+    assert!(skip_subtree::<U8>(&children[..4]).is_err());
+}
+
+#[test]
+fn slice_tree_out_of_forest() {
+    // Given two trees written back to back
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 1).unwrap();
+    let forest = builder.finish_forest().unwrap();
+
+    // When
+    let second = TreeSlice::<U8>::slice_range(&forest, 9..27).unwrap();
+
+    // Then
+    assert_eq!((vec![3, 2], vec![0, 0]), second.to_parent_array());
+    assert!(TreeSlice::<U8>::slice_range(&forest, 0..27).is_err());
+    assert!(TreeSlice::<U8>::slice_range(&forest, 9..28).is_err());
+}
+
+#[test]
+fn try_new_reports_out_of_bounds_size() {
+    // Given
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 2).unwrap();
+    let bytes = builder.finish().unwrap();
+    // Size header of the second child starts at offset 10
+    let mut corrupted = bytes.clone();
+    corrupted[10..18].copy_from_slice(&100u64.to_le_bytes());
+
+    // When
+    let result = TreeVec::<U8>::try_new(corrupted);
+
+    // Then
+    assert_eq!(
+        Some(TreeError::SizeOutOfBounds {
+            offset: 10,
+            claimed: 100,
+            remaining: 10
+        }),
+        result.err()
+    );
+    assert!(TreeSlice::<U8>::from_slice(&bytes).validate().is_ok());
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(TreeVec::<U8>::try_new(trailing).is_err());
+}
+
+#[test]
+fn flatten_roundtrip() {
+    // Given root 4 with children 3 -> 1 and 2
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&3, 1).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&4, 2).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When
+    let flattened = tree.flatten();
+    let rebuilt = TreeVec::<U8>::from_flattened(&flattened).unwrap();
+
+    // Then
+    assert_eq!(vec![(4, 4), (3, 2), (1, 1), (2, 1)], flattened);
+    assert_eq!(tree.to_parent_array(), rebuilt.to_parent_array());
+    assert!(TreeVec::<U8>::from_flattened(&[(4, 2), (3, 2)]).is_err());
+}
+
+#[test]
+fn dedup_savings() {
+    // Given a root with two identical children of 27 bytes each, and a leaf of 9 bytes, which also
+    // occurs within them
+    let child = two_children_fixture();
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    let leaf = TreeVec::<U8>::new(builder.finish().unwrap());
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder
+        .write_node_with_children(&42, &[&child, &child, &leaf])
+        .unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When
+    let savings = tree.dedup_savings();
+
+    // Then
+    assert_eq!(27 + 9, savings);
+    assert_eq!(0, child.dedup_savings());
+}
+
+#[test]
+fn read_children_in_written_order() {
+    // Given
+    let tree = two_children_fixture();
+
+    // When
+    let (value, branches): (u8, OrderedBranches<U8>) = tree.read_node_ordered();
+
+    // Then
+    assert_eq!(3, value);
+    assert_eq!(2, branches.len());
+    let values: Vec<_> = branches.map(|child| child.read_node().0).collect();
+    assert_eq!(vec![1, 2], values);
+}
+
+#[test]
+fn write_node_unique_rejects_equal_siblings() {
+    // Given
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&1, 0).unwrap();
+
+    // When
+    let duplicates = builder.write_node_unique(&3, 3);
+    let unique = builder.write_node_unique(&3, 2);
+
+    // Then
+    assert_eq!(io::ErrorKind::InvalidInput, duplicates.unwrap_err().kind());
+    assert!(unique.is_ok());
+    builder.write_node(&4, 2).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+    assert_eq!(
+        (vec![4, 1, 3, 2, 1], vec![0, 0, 0, 2, 2]),
+        tree.to_parent_array()
+    );
+}
+
+#[test]
+fn consume_branches_from_both_ends() {
+    // Given
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    for value in 1..=4 {
+        builder.write_node(&value, 0).unwrap();
+    }
+    builder.write_node(&5, 4).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When
+    let (_, mut branches) = tree.read_node();
+
+    // Then
+    let value = |child: Option<&TreeSlice<U8>>| child.map(|child| child.read_node().0);
+    assert_eq!(4, branches.len());
+    assert_eq!(Some(4), value(branches.next()));
+    assert_eq!(Some(1), value(branches.next_back()));
+    assert_eq!(2, branches.len());
+    assert_eq!(Some(2), value(branches.next_back()));
+    assert_eq!(Some(3), value(branches.next()));
+    assert_eq!(0, branches.len());
+    assert_eq!(None, value(branches.next_back()));
+    assert_eq!(None, value(branches.next()));
+    let (_, branches) = tree.read_node();
+    assert_eq!(
+        vec![1, 2, 3, 4],
+        branches.rev().map(|c| c.read_node().0).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn finish_requires_single_root() {
+    // Given two nodes without a parent
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    assert_eq!(18, builder.bytes_written());
+
+    // When
+    let result = builder.finish();
+
+    // Then
+    assert_eq!(io::ErrorKind::InvalidData, result.unwrap_err().kind());
+    let empty = TreeBuilder::<U8, _>::new(Vec::new());
+    assert!(empty.finish().is_err());
+}
+
+#[test]
+fn finish_error_hands_back_builder() {
+    // Given two leaves without a parent
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    let error = builder.finish().unwrap_err();
+
+    // When writing the missing parent
+    let mut builder = error.into_builder();
+    builder.write_node(&3, 2).unwrap();
+    let bytes = builder.finish().unwrap();
+
+    // Then
+    assert_eq!(two_children_fixture().as_bytes(), &bytes[..]);
+}
+
+#[test]
+fn last_child() {
+    // Given
+    let tree = two_children_fixture();
+
+    // When
+    let last = tree.last_child().unwrap();
+
+    // Then
+    assert_eq!(2, last.read_node().0);
+    assert!(last.last_child().is_none());
+}
+
+#[test]
+fn validate_against_structural_spec() {
+    // Given a spec for a root with two leaves, whose values are smaller than three
+    let spec = StructuralSpec::new()
+        .level(2..=2)
+        .level_with_values(0..=0, |value: &u8| *value < 3);
+    let conforming = two_children_fixture();
+    let violating = chain_fixture();
+
+    // When
+    let ok = conforming.validate_against(&spec);
+    let errors = violating.validate_against(&spec);
+
+    // Then
+    assert_eq!(Ok(()), ok);
+    assert_eq!(
+        Err(vec![
+            ValidationError::ChildCount {
+                preorder_index: 0,
+                depth: 0,
+                count: 1
+            },
+            ValidationError::ChildCount {
+                preorder_index: 1,
+                depth: 1,
+                count: 1
+            },
+        ]),
+        errors
+    );
+}
+
+#[test]
+fn string_labelled_nodes() {
+    // Given
+    let mut builder = TreeBuilder::<Utf8, _>::new(Vec::new());
+    builder.write_node(&String::new(), 0).unwrap();
+    builder.write_node(&"Grüße 🌳".to_owned(), 0).unwrap();
+    builder.write_node(&"root".to_owned(), 2).unwrap();
+    let bytes = builder.finish().unwrap();
+
+    // When
+    let tree = TreeSlice::<Utf8>::from_slice(&bytes);
+
+    // Then
+    assert_eq!(Ok(()), tree.validate());
+    let (values, _) = tree.to_parent_array();
+    assert_eq!(vec!["root", "", "Grüße 🌳"], values);
+    // Value of the root is located in front of its size header.
+    assert_eq!((12, "root"), Utf8::read_str(&bytes[..(bytes.len() - 8)]));
+}
+
+#[test]
+fn varint_nodes() {
+    // Given values around the boundaries of the number of bytes, each one a child of the root
+    let unsigned = [0, 127, 128, 16_383, 16_384, u64::MAX - 1, u64::MAX];
+    let signed = [0, -1, 1, -64, 64, i64::MIN, i64::MAX];
+    let mut builder = TreeBuilder::<VarU64, _>::new(Vec::new());
+    for value in unsigned {
+        builder.write_node(&value, 0).unwrap();
+    }
+    builder.write_node(&1, unsigned.len()).unwrap();
+    let unsigned_bytes = builder.finish().unwrap();
+    let mut builder = TreeBuilder::<VarI64, _>::new(Vec::new());
+    for value in signed {
+        builder.write_node(&value, 0).unwrap();
+    }
+    builder.write_node(&-1, signed.len()).unwrap();
+    let signed_bytes = builder.finish().unwrap();
+
+    // When
+    let unsigned_tree = TreeSlice::<VarU64>::from_slice(&unsigned_bytes);
+    let signed_tree = TreeSlice::<VarI64>::from_slice(&signed_bytes);
+
+    // Then
+    assert_eq!(Ok(()), unsigned_tree.validate());
+    let values: Vec<_> = unsigned_tree
+        .read_node_ordered()
+        .1
+        .map(|c| c.read_node().0)
+        .collect();
+    assert_eq!(&unsigned[..], &values);
+    let values: Vec<_> = signed_tree
+        .read_node_ordered()
+        .1
+        .map(|c| c.read_node().0)
+        .collect();
+    assert_eq!(&signed[..], &values);
+    // Number of bytes per value, see `total_value_bytes`
+    assert_eq!(
+        1 + 1 + 2 + 2 + 3 + 10 + 10 + 1,
+        unsigned_tree.total_value_bytes()
+    );
+    assert_eq!(
+        1 + 1 + 1 + 1 + 2 + 10 + 10 + 1,
+        signed_tree.total_value_bytes()
+    );
+}
+
+#[test]
+fn inorder_successor_in_search_tree() {
+    // Given a balanced search tree over 1..=7. Pre-order is 4, 2, 1, 3, 6, 5, 7
+    let tree = TreeVec::<U8>::from_sorted(&[1, 2, 3, 4, 5, 6, 7]).unwrap();
+
+    // When
+    let successor = |index| tree.inorder_successor(index).map(|node| node.read_node().0);
+
+    // Then
+    assert_eq!(Some(5), successor(0));
+    assert_eq!(Some(4), successor(3));
+    assert_eq!(Some(2), successor(2));
+    assert_eq!(None, successor(6));
+    assert_eq!(None, successor(7));
+}
+
+#[test]
+fn clone_subtrees_into_reused_buffer() {
+    // Given
+    let tree = two_children_fixture();
+    let mut buf = Vec::new();
+
+    // When
+    for child in tree.read_node_ordered().1 {
+        buf.clear();
+        child.clone_into_buf(&mut buf);
+    }
+
+    // Then the buffer holds the last child
+    let (value, mut branches) = TreeSlice::<U8>::from_slice(&buf).read_node();
+    assert_eq!(2, value);
+    assert!(branches.next().is_none());
+}
+
+#[test]
+fn big_endian_integer_nodes() {
+    /// Writes `value` as a single leaf and returns its bytes and the value read back.
+    fn roundtrip<N: Node>(value: N::Value) -> (Vec<u8>, N::Value) {
+        let mut builder = TreeBuilder::<N, _>::new(Vec::new());
+        builder.write_node(&value, 0).unwrap();
+        let bytes = builder.finish().unwrap();
+        let value = TreeSlice::<N>::from_slice(&bytes).read_node().0;
+        (bytes, value)
+    }
+
+    // When
+    let (bytes_i32, i32_value) = roundtrip::<BeI32>(-2);
+    let (bytes_i64, i64_value) = roundtrip::<BeI64>(0x0102_0304_0506_0708);
+    let (bytes_u32, u32_value) = roundtrip::<BeU32>(0x0102_0304);
+    let (bytes_u64, u64_value) = roundtrip::<BeU64>(u64::MAX - 1);
+
+    // Then values come back, with the most significant byte first in the buffer
+    assert_eq!(-2, i32_value);
+    assert_eq!(&[0xFF, 0xFF, 0xFF, 0xFE], &bytes_i32[..4]);
+    assert_eq!(0x0102_0304_0506_0708, i64_value);
+    assert_eq!(&[1, 2, 3, 4, 5, 6, 7, 8], &bytes_i64[..8]);
+    assert_eq!(0x0102_0304, u32_value);
+    assert_eq!(&[1, 2, 3, 4], &bytes_u32[..4]);
+    assert_eq!(u64::MAX - 1, u64_value);
+    assert_eq!(0xFE, bytes_u64[7]);
+}
+
+#[test]
+fn preorder_size_prefix_describes_descendants() {
+    // Given root 4 with children 3 -> 1 and 2. Pre-order is 4, 3, 1, 2
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&3, 1).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&4, 2).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When
+    let sizes = tree.preorder_size_prefix();
+
+    // Then
+    assert_eq!(vec![4, 2, 1, 1], sizes);
+    let is_descendant = |i: usize, j: usize| i < j && j < i + sizes[i];
+    let (_, parents) = tree.to_parent_array();
+    for (j, &parent) in parents.iter().enumerate().skip(1) {
+        // The parent of each node is one of its ancestors
+        assert!(is_descendant(parent, j));
+    }
+    assert!(!is_descendant(1, 3));
+    assert!(is_descendant(1, 2));
+}
+
+#[test]
+fn custom_integer_node_from_macro() {
+    // Given
+    contigious_tree::impl_int_node!(BeU16, u16, big);
+    let mut builder = TreeBuilder::<BeU16, _>::new(Vec::new());
+    builder.write_node(&0x0102, 0).unwrap();
+    builder.write_node(&0xFFFF, 1).unwrap();
+
+    // When
+    let bytes = builder.finish().unwrap();
+
+    // Then
+    assert_eq!(&[1, 2], &bytes[..2]);
+    let tree = TreeSlice::<BeU16>::from_slice(&bytes);
+    assert_eq!((vec![0xFFFF, 0x0102], vec![0, 0]), tree.to_parent_array());
+}
+
+#[test]
+fn reject_values_exceeding_limit() {
+    // Given a limit of 16 bytes. Strings take 8 bytes for their length in addition to their text
+    let mut builder = TreeBuilder::<Utf8, _>::new(Vec::new()).with_max_value_size(16);
+    builder.write_node(&"eight ch".to_owned(), 0).unwrap();
+
+    // When
+    let result = builder.write_node(&"nine char".to_owned(), 0);
+
+    // Then
+    let error = result.unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidInput, error.kind());
+    assert_eq!(
+        Some(&TreeError::ValueTooLarge {
+            offset: 24,
+            size: 17,
+            limit: 16
+        }),
+        error.get_ref().and_then(|inner| inner.downcast_ref())
+    );
+    // Nothing has been written for the rejected value
+    assert_eq!(24, builder.bytes_written());
+    let bytes = builder.finish().unwrap();
+    assert_eq!(24, bytes.len());
+}
+
+#[test]
+fn iter_depth_first() {
+    // Given
+    let chain = chain_fixture();
+    let siblings = two_children_fixture();
+
+    // When
+    let iter: DepthFirstIter<U8> = chain.iter_depth_first();
+    let chain_nodes: Vec<_> = iter.collect();
+    let sibling_nodes: Vec<_> = siblings.iter_depth_first().collect();
+
+    // Then
+    assert_eq!(vec![(0, 3), (1, 2), (2, 1)], chain_nodes);
+    assert_eq!(vec![(0, 3), (1, 1), (1, 2)], sibling_nodes);
+}
+
+#[test]
+fn num_children_and_is_leaf() {
+    // Given
+    let chain = chain_fixture();
+    let siblings = two_children_fixture();
+    let (_, mut branches) = chain.read_node();
+    let single_child = branches.next().unwrap();
+    let (_, mut branches) = single_child.read_node();
+    let leaf = branches.next().unwrap();
+
+    // Then
+    assert_eq!(0, leaf.num_children());
+    assert!(leaf.is_leaf());
+    assert_eq!(1, single_child.num_children());
+    assert!(!single_child.is_leaf());
+    assert_eq!(2, siblings.num_children());
+    assert!(!siblings.is_leaf());
+    assert_eq!(siblings.read_node().1.count(), siblings.num_children());
+}
+
+#[test]
+fn swap_value_endianness_in_place() {
+    // Given
+    let mut builder = TreeBuilder::<LeI32, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&-2, 0).unwrap();
+    builder.write_node(&0x0102_0304, 2).unwrap();
+    let mut bytes = builder.finish().unwrap();
+
+    // When
+    swap_value_endianness::<LeI32, 4>(&mut bytes).unwrap();
+
+    // Then
+    let tree = TreeSlice::<BeI32>::from_slice(&bytes);
+    assert_eq!(
+        (vec![0x0102_0304, 1, -2], vec![0, 0, 0]),
+        tree.to_parent_array()
+    );
+}
+
+#[test]
+fn swap_value_endianness_rejects_corrupt_headers() {
+    // Given a tree whose second leaf claims more bytes than its parent holds
+    let mut builder = TreeBuilder::<LeI32, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&-2, 0).unwrap();
+    builder.write_node(&0x0102_0304, 2).unwrap();
+    let mut bytes = builder.finish().unwrap();
+    bytes[16..24].copy_from_slice(&1000u64.to_le_bytes());
+    let original = bytes.clone();
+
+    // When
+    let result = swap_value_endianness::<LeI32, 4>(&mut bytes);
+    let too_wide = swap_value_endianness::<BeI64, 8>(&mut bytes[..12]);
+
+    // Then
+    assert_eq!(
+        Err(TreeError::SizeOutOfBounds {
+            offset: 16,
+            claimed: 1000,
+            remaining: 16
+        }),
+        result
+    );
+    assert!(matches!(too_wide, Err(TreeError::Malformed { .. })));
+    assert_eq!(original, bytes);
+}
+
+#[test]
+fn subtrees_with_node_count() {
+    // Given root 4 with children 3 -> 1 and 2
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&3, 1).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&4, 2).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When
+    let small: Vec<_> = tree
+        .subtrees_with_node_count(1..=2)
+        .map(|subtree| subtree.read_node().0)
+        .collect();
+
+    // Then
+    assert_eq!(vec![3, 1, 2], small);
+    assert_eq!(1, tree.subtrees_with_node_count(3..).count());
+}
+
+#[test]
+fn child_by_index() {
+    // Given
+    let tree = two_children_fixture();
+
+    // When
+    let first = tree.child(0).unwrap();
+    let second = tree.child(1).unwrap();
+
+    // Then
+    assert_eq!(1, first.read_node().0);
+    assert_eq!(2, second.read_node().0);
+    assert!(tree.child(2).is_none());
+    assert!(first.child(0).is_none());
+}
+
+#[test]
+fn read_values_into_slice() {
+    // Given
+    let tree = chain_fixture();
+    let mut values = [0u8; 3];
+    let mut too_small = [0u8; 2];
+
+    // When
+    let count = tree.read_values_into(&mut values);
+    let error = tree.read_values_into(&mut too_small);
+
+    // Then
+    assert_eq!(Ok(3), count);
+    assert_eq!([3, 2, 1], values);
+    assert_eq!(
+        Err(TreeError::BufferTooSmall {
+            capacity: 2,
+            required: 3
+        }),
+        error
+    );
+}
+
+#[test]
+fn rewrap_bytes_of_child() {
+    // Given
+    let tree = chain_fixture();
+    let child = tree.child(0).unwrap();
+
+    // When
+    let standalone = TreeVec::<U8>::try_new(child.as_bytes().to_vec()).unwrap();
+
+    // Then
+    assert_eq!(18, child.byte_len());
+    assert_eq!(27, tree.byte_len());
+    assert_eq!(child.to_parent_array(), standalone.to_parent_array());
+    assert_eq!((vec![2, 1], vec![0, 0]), standalone.to_parent_array());
+}
+
+#[test]
+fn diameter() {
+    // Given root 5 with children 3 -> 2 -> 1 and 4. The longest path 1, 2, 3, 5, 4 passes through
+    // the root
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 1).unwrap();
+    builder.write_node(&3, 1).unwrap();
+    builder.write_node(&4, 0).unwrap();
+    builder.write_node(&5, 2).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When
+    let diameter = tree.diameter();
+
+    // Then
+    assert_eq!(4, diameter);
+    assert_eq!(2, chain_fixture().diameter());
+    assert_eq!(0, tree.child(1).unwrap().diameter());
+}
+
+#[test]
+fn debug_format() {
+    // Given root 3 with children 1 -> 0 and 2
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&0, 0).unwrap();
+    builder.write_node(&1, 1).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 2).unwrap();
+    let bytes = builder.finish().unwrap();
+    let mut corrupted = bytes.clone();
+    // Size header of the first child claims more bytes than are available
+    corrupted[10..18].copy_from_slice(&100u64.to_le_bytes());
+
+    // When
+    let formatted = format!("{:?}", TreeVec::<U8>::new(bytes));
+    let formatted_corrupt = format!("{:?}", TreeSlice::<U8>::from_slice(&corrupted));
+
+    // Then
+    assert_eq!("3\n  1\n    0\n  2", formatted);
+    assert_eq!("3\n  <corrupt>\n  2", formatted_corrupt);
+}
+
+#[test]
+fn sibling_groups() {
+    // Given
+    let tree = two_children_fixture();
+
+    // When
+    let groups: Vec<Vec<u8>> = tree
+        .sibling_groups()
+        .map(|group| group.iter().map(|child| child.read_node().0).collect())
+        .collect();
+
+    // Then
+    assert_eq!(vec![vec![1, 2]], groups);
+    assert_eq!(2, chain_fixture().sibling_groups().count());
+}
+
+#[test]
+fn structural_equality() {
+    // Given the same logical tree with different encodings and a tree with another shape
+    let mut builder = TreeBuilder::<LeI32, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 2).unwrap();
+    let little = TreeVec::<LeI32>::new(builder.finish().unwrap());
+    let mut builder = TreeBuilder::<BeI32, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 2).unwrap();
+    let big = TreeVec::<BeI32>::new(builder.finish().unwrap());
+    let mut builder = TreeBuilder::<LeI32, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 1).unwrap();
+    builder.write_node(&3, 1).unwrap();
+    let chain = TreeVec::<LeI32>::new(builder.finish().unwrap());
+
+    // Then
+    assert!(little == big);
+    assert!(little != chain);
+    assert!(*little.child(0).unwrap() == *chain.child(0).unwrap().child(0).unwrap());
+    assert_eq!(two_children_fixture(), two_children_fixture());
+    assert_ne!(two_children_fixture(), chain_fixture());
+}
+
+#[test]
+fn index_values_while_building() {
+    // Given
+    let mut builder = IndexingTreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 1).unwrap();
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&3, 2).unwrap();
+
+    // When
+    let (bytes, index) = builder.finish().unwrap();
+
+    // Then
+    assert_eq!(vec![0..9, 18..27], index[&1]);
+    let subtree = TreeSlice::<U8>::from_slice(&bytes[index[&2][0].clone()]);
+    assert_eq!((vec![2, 1], vec![0, 0]), subtree.to_parent_array());
+    assert_eq!(vec![0..bytes.len()], index[&3]);
+    assert!(!index.contains_key(&4));
+}
+
+#[test]
+fn write_recursive_tree() {
+    // Given
+    #[derive(Debug, PartialEq)]
+    struct Tree {
+        value: i32,
+        children: Vec<Tree>,
+    }
+    impl IntoTree for Tree {
+        type Value = i32;
+
+        fn value(&self) -> &i32 {
+            &self.value
+        }
+
+        fn children(&self) -> impl Iterator<Item = &Self> {
+            self.children.iter()
+        }
+    }
+    fn from_slice(slice: &TreeSlice<LeI32>) -> Tree {
+        let (value, children) = slice.read_node_ordered();
+        Tree {
+            value,
+            children: children.map(from_slice).collect(),
+        }
+    }
+    let leaf = |value| Tree {
+        value,
+        children: Vec::new(),
+    };
+    let tree = Tree {
+        value: 1,
+        children: vec![
+            Tree {
+                value: 2,
+                children: vec![leaf(3), leaf(4)],
+            },
+            leaf(5),
+        ],
+    };
+
+    // When
+    let mut builder = TreeBuilder::<LeI32, _>::new(Vec::new());
+    builder.write_tree(&tree).unwrap();
+    let tree_vec = TreeVec::<LeI32>::new(builder.finish().unwrap());
+
+    // Then
+    assert_eq!(
+        (vec![1, 2, 3, 4, 5], vec![0, 0, 1, 1, 0]),
+        tree_vec.to_parent_array()
+    );
+    assert_eq!(tree, from_slice(&tree_vec));
+}
+
+#[test]
+fn rewrite_collapses_single_child_nodes() {
+    // Given 1 with children [2 -> 3 -> 4, 5]
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&4, 0).unwrap();
+    builder.write_node(&3, 1).unwrap();
+    builder.write_node(&2, 1).unwrap();
+    builder.write_node(&5, 0).unwrap();
+    builder.write_node(&1, 2).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When replacing each node with a single child by that child
+    let rewritten = tree
+        .rewrite_fixpoint(|_value, children| match children {
+            [child] => {
+                let (value, grand_children) = child.read_node_ordered();
+                let grand_children = grand_children
+                    .map(|grand_child| TreeVec::new(grand_child.as_bytes().to_vec()))
+                    .collect();
+                Some((value, grand_children))
+            }
+            _ => None,
+        })
+        .unwrap();
+
+    // Then
+    assert_eq!((vec![1, 4, 5], vec![0, 0, 0]), rewritten.to_parent_array());
+    // A tree without single child nodes is its own fixpoint
+    assert_eq!(rewritten, rewritten.rewrite_fixpoint(|_, _| None).unwrap());
+}
+
+#[test]
+fn seekable_tree_read_root() {
+    // Given
+    let bytes = two_children_fixture().as_bytes().to_vec();
+    let mut tree = SeekableTree::<U8, _>::new(io::Cursor::new(bytes.clone()), 1).unwrap();
+
+    // When traversing the children of the root twice
+    let mut traverse = || {
+        let (value, branches) = tree.read_root().unwrap();
+        let children: Vec<_> = branches.map(Result::unwrap).collect();
+        (value, children)
+    };
+    let first = traverse();
+    let second = traverse();
+
+    // Then
+    assert_eq!(3, first.0);
+    assert_eq!(2, first.1.len());
+    assert_eq!(first, second);
+    // Truncating the tree is detected, rather than causing a panic. Unless only the first child
+    // remains, which is a complete tree of its own.
+    for len in (0..bytes.len()).filter(|&len| len != 9) {
+        let truncated = io::Cursor::new(bytes[..len].to_vec());
+        let mut tree = SeekableTree::<U8, _>::new(truncated, 1).unwrap();
+        let error = tree.read_root().err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+    }
+}
+
+#[test]
+fn stream_values_through_bounded_channel() {
+    // Given
+    let tree = two_children_fixture();
+    let (sender, receiver) = std::sync::mpsc::sync_channel(1);
+
+    // When
+    let consumer = std::thread::spawn(move || receiver.iter().collect::<Vec<_>>());
+    tree.stream_values_to(sender).unwrap();
+    let values = consumer.join().unwrap();
+
+    // Then
+    assert_eq!(vec![3, 1, 2], values);
+}
+
+#[test]
+fn read_borrowed_bytes() {
+    // Given
+    let mut builder = TreeBuilder::<Bytes, _>::new(Vec::new());
+    builder.write_node(&vec![4, 5], 0).unwrap();
+    builder.write_node(&vec![1, 2, 3], 1).unwrap();
+    let tree = TreeVec::<Bytes>::new(builder.finish().unwrap());
+
+    // When
+    let (value, mut branches) = tree.read_node_borrowed();
+    let (child_value, _) = branches.next().unwrap().read_node_borrowed();
+
+    // Then value points into the tree
+    assert_eq!(&[1, 2, 3], value);
+    assert_eq!(&[4, 5], child_value);
+    let range = tree.as_bytes().as_ptr_range();
+    assert!(range.contains(&value.as_ptr()));
+    assert_eq!(vec![1, 2, 3], tree.read_node().0);
+}
+
+#[test]
+fn minimal_cover_of_leaves() {
+    // Given 1 with children [2 with leaves [3, 4], 5 with leaf 6, leaf 7]
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&3, 0).unwrap();
+    builder.write_node(&4, 0).unwrap();
+    builder.write_node(&2, 2).unwrap();
+    builder.write_node(&6, 0).unwrap();
+    builder.write_node(&5, 1).unwrap();
+    builder.write_node(&7, 0).unwrap();
+    builder.write_node(&1, 3).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When selecting leaves 4 and 7
+    let cover = tree.minimal_cover(&[1, 3]).unwrap();
+
+    // Then
+    assert_eq!(
+        (vec![1, 2, 4, 7], vec![0, 0, 1, 0]),
+        cover.to_parent_array()
+    );
+    assert_eq!(
+        (vec![1], vec![0]),
+        tree.minimal_cover(&[]).unwrap().to_parent_array()
+    );
+    let error = tree.minimal_cover(&[4]).err().unwrap();
+    assert_eq!(io::ErrorKind::InvalidInput, error.kind());
+}
+
+#[test]
+fn common_prefix_depth() {
+    // Given two trees sharing root 3 and its children 1, 2, but not the grand children
+    let tree = two_children_fixture();
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&4, 0).unwrap();
+    builder.write_node(&2, 1).unwrap();
+    builder.write_node(&3, 2).unwrap();
+    let other = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When
+    let depth = tree.common_prefix_depth(&other);
+
+    // Then
+    assert_eq!(2, depth);
+    assert_eq!(3, other.common_prefix_depth(&other));
+    assert_eq!(
+        0,
+        tree.common_prefix_depth(chain_fixture().child(0).unwrap())
+    );
+}
+
+#[test]
+fn navigate_by_path() {
+    // Given 1 with children [2 with children [3, 4], 5]
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&3, 0).unwrap();
+    builder.write_node(&4, 0).unwrap();
+    builder.write_node(&2, 2).unwrap();
+    builder.write_node(&5, 0).unwrap();
+    builder.write_node(&1, 2).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When
+    let root = tree.navigate(&[]);
+    let four = tree.navigate(&[0, 1]);
+    let off_the_edge = tree.navigate(&[1, 0]);
+
+    // Then
+    assert_eq!(tree.as_bytes(), root.unwrap().as_bytes());
+    assert_eq!(4, four.unwrap().read_node().0);
+    assert!(off_the_edge.is_none());
+    assert!(tree.navigate(&[2]).is_none());
+}
+
+#[test]
+fn write_node_with_too_many_children() {
+    // Given
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+
+    // When
+    let error = builder.write_node(&3, 5).unwrap_err();
+
+    // Then
+    assert_eq!(io::ErrorKind::InvalidInput, error.kind());
+    assert_eq!(
+        "Requested 5 children, but only 2 subtrees are open.",
+        error.to_string()
+    );
+    // Nothing has been written, so the builder can still be used
+    builder.write_node(&3, 2).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+    assert_eq!(2, tree.num_children());
+}
+
+#[test]
+fn reroot_separately_built_trees() {
+    // Given
+    let chain = chain_fixture();
+    let two_children = two_children_fixture();
+
+    // When
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder
+        .write_node_with_children(&42, &[&chain, &two_children])
+        .unwrap();
+    let rerooted = builder.finish().unwrap();
+
+    // Then the bytes are identical to writing all nodes in one pass
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 1).unwrap();
+    builder.write_node(&3, 1).unwrap();
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 2).unwrap();
+    builder.write_node(&42, 2).unwrap();
+    let single_pass = builder.finish().unwrap();
+    assert_eq!(single_pass, rerooted);
+}
+
+#[test]
+fn depth_and_node_count() {
+    // Given
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    let leaf = TreeVec::<U8>::new(builder.finish().unwrap());
+    let chain = chain_fixture();
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 0).unwrap();
+    builder.write_node(&4, 2).unwrap();
+    builder.write_node(&5, 2).unwrap();
+    let bushy = TreeVec::<U8>::new(builder.finish().unwrap());
+
+    // When
+    let depths = [leaf.depth(), chain.depth(), bushy.depth()];
+    let node_counts = [leaf.node_count(), chain.node_count(), bushy.node_count()];
+
+    // Then
+    assert_eq!([1, 3, 3], depths);
+    assert_eq!([1, 3, 5], node_counts);
+}
+
+#[test]
+fn is_single_rooted() {
+    // Given
+    let tree = two_children_fixture();
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    let forest = builder.finish_forest().unwrap();
+    let truncated = &tree.as_bytes()[1..];
+
+    // When
+    let single = tree.is_single_rooted();
+    let forest_single = TreeSlice::<U8>::from_slice(&forest).is_single_rooted();
+    let truncated_single = TreeSlice::<U8>::from_slice(truncated).is_single_rooted();
+
+    // Then
+    assert!(single);
+    assert!(!forest_single);
+    assert!(!truncated_single);
+    assert!(!TreeSlice::<U8>::from_slice(&[]).is_single_rooted());
+}
+
+#[test]
+fn builder_with_capacity() {
+    // Given a tree of three nodes, taking 27 bytes
+    let mut builder = TreeBuilder::<U8, _>::with_capacity(27, 2);
+
+    // When
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 2).unwrap();
+    let bytes = builder.finish().unwrap();
+
+    // Then the buffer did not grow and holds the same bytes
+    assert_eq!(27, bytes.capacity());
+    assert_eq!(two_children_fixture().as_bytes(), bytes.as_slice());
+}
+
+#[test]
+fn builder_with_max_open_nodes() {
+    // Given
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new()).with_max_open_nodes(2);
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+
+    // When
+    let third_leaf = builder.write_node(&3, 0);
+    let subtree = builder.write_subtree(&chain_fixture());
+
+    // Then
+    assert_eq!(io::ErrorKind::InvalidInput, third_leaf.unwrap_err().kind());
+    assert_eq!(io::ErrorKind::InvalidInput, subtree.unwrap_err().kind());
+    // Adopting the open nodes stays within the limit
+    builder.write_node(&3, 2).unwrap();
+    assert_eq!(
+        two_children_fixture().as_bytes(),
+        builder.finish().unwrap().as_slice()
+    );
+}
+
+#[test]
+fn clone_half_consumed_branches() {
+    // Given 4 with children 1, 2, 3
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 0).unwrap();
+    builder.write_node(&4, 3).unwrap();
+    let tree = TreeVec::<U8>::new(builder.finish().unwrap());
+    let (_, mut branches) = tree.read_node();
+    branches.next().unwrap();
+
+    // When
+    let copy = branches;
+    let remaining: Vec<_> = branches.map(|child| child.read_node().0).collect();
+    let remaining_copy: Vec<_> = copy.map(|child| child.read_node().0).collect();
+
+    // Then
+    assert_eq!(vec![2, 1], remaining);
+    assert_eq!(remaining, remaining_copy);
+    assert_eq!(&tree.as_bytes()[..18], copy.remaining_bytes());
+}
+
+#[test]
+fn fold_evaluates_expression() {
+    // Given (2 + 3) * (10 - 4), with operators encoded as negative numbers
+    const ADD: i32 = -1;
+    const SUB: i32 = -2;
+    const MUL: i32 = -3;
+    let mut builder = TreeBuilder::<LeI32, _>::new(Vec::new());
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 0).unwrap();
+    builder.write_node(&ADD, 2).unwrap();
+    builder.write_node(&10, 0).unwrap();
+    builder.write_node(&4, 0).unwrap();
+    builder.write_node(&SUB, 2).unwrap();
+    builder.write_node(&MUL, 2).unwrap();
+    let tree = TreeVec::<LeI32>::new(builder.finish().unwrap());
+
+    // When
+    let result = tree.fold(|value, operands: Vec<i32>| match value {
+        ADD => operands.iter().sum(),
+        SUB => operands[0] - operands[1],
+        MUL => operands.iter().product(),
+        number => number,
+    });
+
+    // Then
+    assert_eq!(30, result);
+}
+
+#[test]
+#[should_panic(expected = "Value must be a varint of at most 10 bytes.")]
+fn varint_with_too_many_continuation_bytes() {
+    // Given twelve bytes, each one with its continuation bit set
+    let bytes = [0x80; 12];
+
+    // When
+    VarU64::read_value(&bytes);
+}
+
+#[test]
+fn reject_subtrees_too_small_for_their_value() {
+    // Given a root with value 42, whose only child claims just 2 bytes for a 4 byte value
+    let mut bytes = vec![1, 2];
+    bytes.extend_from_slice(&2u64.to_le_bytes());
+    bytes.extend_from_slice(&42i32.to_le_bytes());
+    bytes.extend_from_slice(&14u64.to_le_bytes());
+    let tree = TreeSlice::<LeI32>::from_slice(&bytes);
+    // And a varint value, which is never terminated
+    let mut varint_bytes = vec![0x80; 12];
+    varint_bytes.extend_from_slice(&12u64.to_le_bytes());
+
+    // When
+    let result = tree.validate();
+    let (_, mut branches) = tree.read_node();
+    let child = branches.checked_next();
+    let varint_result = TreeVec::<VarU64>::try_new(varint_bytes).map(|_| ());
+
+    // Then
+    let malformed = TreeError::Malformed {
+        reason: "Subtree does not hold a valid value.",
+    };
+    assert_eq!(Err(malformed.clone()), result);
+    assert!(matches!(child, Some(Err(TreeError::Malformed { .. }))));
+    assert_eq!(Err(malformed), varint_result);
+}
+
+#[test]
+fn finish_with_index_requires_single_root() {
+    // Given
+    let empty = TreeBuilder::<U8, _>::new(Vec::new()).with_index();
+    let mut forest = TreeBuilder::<U8, _>::new(Vec::new()).with_index();
+    forest.write_node(&1, 0).unwrap();
+    forest.write_node(&2, 0).unwrap();
+    let mut not_enabled = TreeBuilder::<U8, _>::new(Vec::new());
+    not_enabled.write_node(&1, 0).unwrap();
+    let mut enabled_late = TreeBuilder::<U8, _>::new(Vec::new());
+    enabled_late.write_node(&1, 0).unwrap();
+    let mut enabled_late = enabled_late.with_index();
+    enabled_late.write_node(&2, 1).unwrap();
+    let mut copied = TreeBuilder::<U8, _>::new(Vec::new()).with_index();
+    copied.write_subtree(&chain_fixture()).unwrap();
+    copied.write_node(&4, 1).unwrap();
+
+    // When
+    let empty = empty.finish_with_index();
+    let forest = forest.finish_with_index();
+    let not_enabled = not_enabled.finish_with_index();
+    let enabled_late = enabled_late.finish_with_index();
+    let (_, copied_index) = copied.finish_with_index().unwrap();
+
+    // Then
+    assert_eq!(io::ErrorKind::InvalidData, empty.unwrap_err().kind());
+    assert_eq!(io::ErrorKind::InvalidData, forest.unwrap_err().kind());
+    assert_eq!(io::ErrorKind::InvalidInput, not_enabled.unwrap_err().kind());
+    assert_eq!(
+        io::ErrorKind::InvalidInput,
+        enabled_late.unwrap_err().kind()
+    );
+    // Nodes of copied subtrees are indexed, too
+    assert_eq!(vec![0..36, 0..27, 0..18, 0..9], copied_index);
+}
+
+#[test]
+fn packed_bools_reject_invalid_group_sizes() {
+    // Given
+    let too_many = vec![true; 9];
+    let corrupt_len = [0xFF, 9];
+
+    // When
+    let write_result = PackedBools::write_value(&mut Vec::new(), &too_many);
+    let read_result = PackedBools::try_read_value(&corrupt_len);
+
+    // Then
+    assert_eq!(
+        io::ErrorKind::InvalidInput,
+        write_result.unwrap_err().kind()
+    );
+    assert_eq!(None, read_result);
+    assert_eq!(
+        Some((2, vec![true, false])),
+        PackedBools::try_read_value(&[1, 2])
+    );
+}
+
+#[test]
+fn seekable_tree_rejects_values_beyond_window() {
+    // Given a root with value 42, whose only child claims just 2 bytes for a 4 byte value
+    let mut bytes = vec![1, 2];
+    bytes.extend_from_slice(&2u64.to_le_bytes());
+    bytes.extend_from_slice(&42i32.to_le_bytes());
+    bytes.extend_from_slice(&14u64.to_le_bytes());
+    let mut tree = SeekableTree::<LeI32, _>::new(io::Cursor::new(bytes.clone()), 4).unwrap();
+    // And a window too small for the values
+    let mut narrow = SeekableTree::<LeI32, _>::new(io::Cursor::new(bytes), 2).unwrap();
+
+    // When
+    let child = {
+        let (_, mut branches) = tree.read_root().unwrap();
+        branches.next().unwrap().unwrap()
+    };
+    let child_result = tree.read_node(child).map(|(value, _)| value);
+    let narrow_result = narrow.read_root().map(|(value, _)| value);
+
+    // Then
+    let error = child_result.unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidData, error.kind());
+    assert_eq!(
+        "Subtree does not hold a valid value within the window.",
+        error.to_string()
+    );
+    assert_eq!(
+        io::ErrorKind::InvalidData,
+        narrow_result.unwrap_err().kind()
+    );
+}
+
+#[test]
+fn from_columnar_rejects_garbage_structure() {
+    // Given a single value, whose size header claims far more bytes than present
+    let values = 42i32.to_le_bytes();
+    let mut structure = 4u64.to_le_bytes().to_vec();
+    structure.extend_from_slice(&1000u64.to_le_bytes());
+
+    // When
+    let result = TreeVec::<LeI32>::from_columnar(&values, &structure);
+
+    // Then
+    assert!(matches!(
+        result,
+        Err(TreeError::SizeOutOfBounds { claimed: 1000, .. })
+    ));
+}
+
+#[test]
+fn crc_footer_requires_single_root() {
+    // Given
+    let empty = TreeBuilder::<U8, _>::new(Vec::new());
+    let mut forest = TreeBuilder::<U8, _>::new(Vec::new());
+    forest.write_node(&1, 0).unwrap();
+    forest.write_node(&2, 0).unwrap();
+
+    // When
+    let empty = empty.finish_with_crc_footer();
+    let forest = forest.finish_with_crc_footer();
+
+    // Then
+    assert_eq!(io::ErrorKind::InvalidData, empty.unwrap_err().kind());
+    assert_eq!(io::ErrorKind::InvalidData, forest.unwrap_err().kind());
+}
+
+/// Chain of three nodes. Root has value 3, its child 2 and its grandchild 1.
+fn chain_fixture() -> TreeVec<U8> {
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 1).unwrap();
+    builder.write_node(&3, 1).unwrap();
+    TreeVec::new(builder.finish().unwrap())
+}
+
+/// Root with value 3 and two leaves as children. The first written child has value 1, the second 2.
+fn two_children_fixture() -> TreeVec<U8> {
+    let mut builder = TreeBuilder::<U8, _>::new(Vec::new());
+    builder.write_node(&1, 0).unwrap();
+    builder.write_node(&2, 0).unwrap();
+    builder.write_node(&3, 2).unwrap();
+    TreeVec::new(builder.finish().unwrap())
+}
+
+/// Wraps a reader and counts the number of bytes read from it.
+struct CountingReader<R> {
+    inner: R,
+    bytes_read: usize,
+}
+
+impl<R> CountingReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            bytes_read: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let num_bytes = self.inner.read(buf)?;
+        self.bytes_read += num_bytes;
+        Ok(num_bytes)
+    }
+}
+
+impl<R: Seek> Seek for CountingReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
@@ -0,0 +1,21 @@
+//! Run with `cargo test --features testing`.
+#![cfg(feature = "testing")]
+
+use contigious_tree::{
+    testing::assert_node_roundtrips, BeI32, BeI64, BeU32, BeU64, Bool, LeI32, Utf8, VarI64, VarU64,
+    U8,
+};
+
+#[test]
+fn provided_nodes_roundtrip() {
+    assert_node_roundtrips::<LeI32>(&[0, 1, -1, i32::MIN, i32::MAX]);
+    assert_node_roundtrips::<U8>(&[0, 42, u8::MAX]);
+    assert_node_roundtrips::<BeI32>(&[0, -1, i32::MIN, i32::MAX]);
+    assert_node_roundtrips::<BeI64>(&[0, -1, i64::MIN, i64::MAX]);
+    assert_node_roundtrips::<BeU32>(&[0, u32::MAX]);
+    assert_node_roundtrips::<BeU64>(&[0, u64::MAX]);
+    assert_node_roundtrips::<Bool>(&[true, false]);
+    assert_node_roundtrips::<VarU64>(&[0, 127, 128, u64::MAX]);
+    assert_node_roundtrips::<VarI64>(&[0, -65, 64, i64::MIN, i64::MAX]);
+    assert_node_roundtrips::<Utf8>(&[String::new(), "Grüße 🌳".to_owned()]);
+}